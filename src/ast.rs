@@ -5,14 +5,33 @@
 //GOAL: To know what the character means that are recieved from the lexer
 //Defining the AST structures
 
-use crate::token::Token;
+use crate::token::{Span, Token};
+use std::fmt;
 
+// Every expression and statement carries the span of the source it was parsed from,
+// so errors anywhere after parsing can point back at `file.vx:line:col`.
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expr {
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
     Number(i64),
     Floating(f64),
+    Int32(i32),
+    UInt32(u32),
+    Float32(f32),
     Boolean(bool),
     String(String),
+    InterpolatedString(Vec<StringSegment>),
     Ident(String),
     Unary {
         op: Token,
@@ -20,11 +39,19 @@ pub enum Expr {
     },
     Binary {
         left: Box<Expr>,
-        op: crate::token::Token,
+        op: Token,
         right: Box<Expr>,
     },
+    // `target = value`; the target is a variable, an index expression or a field (the
+    // parser rejects anything else)
     Assignment {
-        name: String,
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    // `target op= value`, read-modify-write of a single evaluated target
+    CompoundAssignment {
+        target: Box<Expr>,
+        op: Token,
         value: Box<Expr>,
     },
     Grouping(Box<Expr>),
@@ -32,20 +59,200 @@ pub enum Expr {
         callee: Box<Expr>,
         arguments: Vec<Expr>, // not boxed unless needed
     },
-    Return(Box<Expr>),
+    // `start..end step n`; either bound may be left out in slices (`a[..n]`, `a[2..]`)
+    Range {
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+        step: Option<Box<Expr>>,
+    },
+    Array(Vec<Expr>),       // `[a, b, c]`
+    Tuple(Vec<Expr>),       // `(a, b)`; `(a,)` has one element
+    Map(Vec<(Expr, Expr)>), // `{key: value, ...}`; `{}` is an empty map
+    Set(Vec<Expr>),         // `{a, b, c}`
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Field {
+        object: Box<Expr>,
+        name: String,
+    },
+    // `receiver.method(args)`, dispatched on the receiver's runtime type
+    MethodCall {
+        receiver: Box<Expr>,
+        method: String,
+        arguments: Vec<Expr>,
+    },
+    // `match subject:` and an indented block of `pattern if guard => body` arms. Used as a
+    // statement or for its value, which is that of the arm that ran.
+    Match {
+        subject: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
+    // `fn(x, y) => x + y`, an anonymous function; the body is `return <expr>`
+    Lambda {
+        params: Vec<Param>,
+        body: Box<Stmt>,
+    },
+    // `Matrix { rows: 2, cols: 2, data: [...] }`
+    StructLiteral {
+        name: String,
+        fields: Vec<(String, Expr)>,
+    },
+}
+
+// A parameter of a function or lambda; the type is optional
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Option<String>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Stmt,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    Wildcard, // `_`
+    // A bare name binds the value, unless some enum has a variant of that name
+    Binding(String),
+    Literal(Expr), // number, string or boolean literal
+    // `lo..hi`, matching numbers from `lo` up to but not including `hi`
     Range {
-        start: Box<Expr>,
-        end: Box<Expr>,
+        start: Expr,
+        end: Expr,
+    },
+    // `Circle(r)`, `Shape.Rect(w, _)` or `Shape.Empty`
+    Variant {
+        enum_name: Option<String>,
+        name: String,
+        fields: Vec<Pattern>,
+    },
+    Tuple(Vec<Pattern>), // `(q, r)`
+    Array(Vec<Pattern>), // `[first, second]`, for arrays of exactly that length
+    // `Point { x, y: 0 }`; `x` alone binds field `x`, fields left out match anything
+    Struct {
+        name: String,
+        fields: Vec<(String, Pattern)>,
     },
 }
 
+// Patterns print as written, for messages about them
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn list(f: &mut fmt::Formatter, patterns: &[Pattern]) -> fmt::Result {
+            for (i, pattern) in patterns.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", pattern)?;
+            }
+            Ok(())
+        }
+        fn literal(f: &mut fmt::Formatter, expr: &Expr) -> fmt::Result {
+            match &expr.kind {
+                ExprKind::Number(n) => write!(f, "{}", n),
+                ExprKind::Floating(x) => write!(f, "{:?}", x),
+                ExprKind::String(s) => write!(f, "{:?}", s),
+                ExprKind::Boolean(b) => write!(f, "{}", b),
+                _ => write!(f, "..."),
+            }
+        }
+        match &self.kind {
+            PatternKind::Wildcard => write!(f, "_"),
+            PatternKind::Binding(name) => write!(f, "{}", name),
+            PatternKind::Literal(expr) => literal(f, expr),
+            PatternKind::Range { start, end } => {
+                literal(f, start)?;
+                write!(f, "..")?;
+                literal(f, end)
+            }
+            PatternKind::Variant { enum_name, name, fields } => {
+                if let Some(enum_name) = enum_name {
+                    write!(f, "{}.", enum_name)?;
+                }
+                write!(f, "{}", name)?;
+                if !fields.is_empty() {
+                    write!(f, "(")?;
+                    list(f, fields)?;
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            PatternKind::Tuple(elements) => {
+                write!(f, "(")?;
+                list(f, elements)?;
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            PatternKind::Array(elements) => {
+                write!(f, "[")?;
+                list(f, elements)?;
+                write!(f, "]")
+            }
+            PatternKind::Struct { name, fields } => {
+                write!(f, "{} {{ ", name)?;
+                for (i, (field, pattern)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match &pattern.kind {
+                        PatternKind::Binding(bound) if bound == field => write!(f, "{}", field)?,
+                        _ => write!(f, "{}: {}", field, pattern)?,
+                    }
+                }
+                write!(f, " }}")
+            }
+        }
+    }
+}
+
+// One piece of an interpolated string: literal text or an embedded `${expr}`
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringSegment {
+    Literal(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Stmt { kind, span }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Stmt {
+pub enum StmtKind {
     Let {
         name: String,
         type_name: Option<String>,
         value: Expr,
         mutable: bool,
+        doc: Option<String>, // from `///` comments right above the declaration
+    },
+    // `let (q, r) = divmod(a, b)`: binds every name in the pattern
+    LetPattern {
+        pattern: Pattern,
+        value: Expr,
+        mutable: bool,
     },
     ExprStmt(Expr),
     Block(Vec<Stmt>),
@@ -58,16 +265,43 @@ pub enum Stmt {
 
     FunctionDef {
         name: String,
-        params: Vec<(String, Option<String>)>, // name + optional type
+        params: Vec<Param>,
         return_type: Option<String>,
         body: Box<Stmt>,
         gpu: bool,
+        doc: Option<String>, // from `///` comments right above the declaration
+    },
+
+    // `struct Matrix: rows: Int, cols: Int`, or one field per line in an indented block
+    Struct {
+        name: String,
+        fields: Vec<(String, String)>, // name + type
+        doc: Option<String>,
+    },
+
+    // `enum Shape: Circle(Float), Rect(Float, Float), Empty`, or one variant per line
+    Enum {
+        name: String,
+        variants: Vec<(String, Vec<String>)>, // name + payload types
+        doc: Option<String>,
+    },
+
+    // `impl Matrix:` and a block of `fn` definitions taking `self` first
+    Impl {
+        type_name: String,
+        methods: Vec<Stmt>, // FunctionDef statements
     },
 
     For {
-        var: String,
+        pattern: Pattern, // the loop variable, or a pattern such as `(i, x)`
         range: Expr,
         body: Box<Stmt>,
+        label: Option<String>, // `outer: for ...`, for `break outer` / `continue outer`
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+        label: Option<String>,
     },
     Parallel {
         var: String,
@@ -75,10 +309,14 @@ pub enum Stmt {
         body: Box<Stmt>,
     },
 
+    // Consecutive `branch cond => ...` arms and an optional trailing `fallback => ...`.
+    // Outside `parallel` the first arm whose condition holds runs; inside it every lane
+    // evaluates all conditions and then runs its own first matching arm.
     Branch {
-        condition: Expr,
-        body: Box<Stmt>,
+        arms: Vec<(Expr, Stmt)>, // condition + body
+        fallback: Option<Box<Stmt>>,
     },
-    Fallback(Box<Stmt>),
     Return(Expr),
+    Break(Option<String>),    // optional label of the loop to leave
+    Continue(Option<String>), // optional label of the loop to continue
 }
@@ -0,0 +1,200 @@
+// || shree ganesh ||
+// Built-in functions and methods for working with strings, arrays, maps and sets
+
+use crate::interpreter::{ArraySlice, Interpreter, Key, Value};
+use crate::numeric::{self, NumericType};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+const BUILTINS: &[&str] = &[
+    "keys", "values", "contains", "set", "insert", "remove", "enumerate", "i32", "u32", "i64", "f32", "f64",
+];
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name)
+}
+
+// Runs the named built-in; user-defined functions of the same name take precedence
+pub fn call(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    // i32(x), f32(x) and the other conversions, named like the literal suffixes
+    if let (Some(to), [value]) = (NumericType::from_suffix(name), args.as_slice()) {
+        return numeric::convert(value, to);
+    }
+    match (name, args.as_slice()) {
+        // keys(m) / values(m): arrays in the map's (sorted) key order
+        ("keys", [Value::Map(entries)]) => {
+            Ok(Value::array(entries.borrow().keys().map(Key::to_value).collect()))
+        }
+        ("values", [Value::Map(entries)]) => Ok(Value::array(entries.borrow().values().cloned().collect())),
+        ("contains", [container, item]) => contains(container, item).map(Value::Boolean),
+        // enumerate(xs): `(index, element)` tuples, for `for (i, x) in enumerate(xs):`
+        ("enumerate", [sequence]) => {
+            let elements = match sequence {
+                Value::String(s) => s.chars().map(|c| Value::String(c.to_string())).collect(),
+                other => other
+                    .as_slice()
+                    .ok_or_else(|| format!("expected an Array or String, not {}", other.type_name()))?
+                    .elements(),
+            };
+            let pairs = elements
+                .into_iter()
+                .enumerate()
+                .map(|(i, element)| Value::Tuple(Rc::new(vec![Value::Number(i as i64), element])))
+                .collect();
+            Ok(Value::array(pairs))
+        }
+        ("set", []) => Ok(Value::set(BTreeSet::new())),
+        ("set", [sequence]) => {
+            let elements = sequence
+                .as_slice()
+                .ok_or_else(|| format!("expected an Array, not {}", sequence.type_name()))?
+                .elements();
+            let set = elements.iter().map(Key::from_value).collect::<Result<BTreeSet<_>, _>>()?;
+            Ok(Value::set(set))
+        }
+        // insert(s, x) returns whether x was new to the set
+        ("insert", [Value::Set(elements), item]) => {
+            Ok(Value::Boolean(elements.borrow_mut().insert(Key::from_value(item)?)))
+        }
+        // remove(c, k) returns whether k was present
+        ("remove", [Value::Map(entries), key]) => {
+            Ok(Value::Boolean(entries.borrow_mut().remove(&Key::from_value(key)?).is_some()))
+        }
+        ("remove", [Value::Set(elements), item]) => {
+            Ok(Value::Boolean(elements.borrow_mut().remove(&Key::from_value(item)?)))
+        }
+        _ => Err(format!("unsupported arguments ({})", type_names(&args))),
+    }
+}
+
+// Built-in methods of strings, arrays, maps and sets, for `value.method(args)` calls that
+// no `impl` block answers. Collection functions above double as methods (`m.keys()`).
+pub fn call_method(receiver: &Value, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    match (receiver, method, args.as_slice()) {
+        (Value::String(s), "length", []) => Ok(Value::Number(s.chars().count() as i64)),
+        (Value::String(s), "upper", []) => Ok(Value::String(s.to_uppercase())),
+        (Value::String(s), "lower", []) => Ok(Value::String(s.to_lowercase())),
+        (Value::String(s), "trim", []) => Ok(Value::String(s.trim().to_string())),
+        (Value::String(s), "starts_with", [Value::String(prefix)]) => Ok(Value::Boolean(s.starts_with(prefix.as_str()))),
+        (Value::String(s), "ends_with", [Value::String(suffix)]) => Ok(Value::Boolean(s.ends_with(suffix.as_str()))),
+        (Value::String(s), "replace", [Value::String(from), Value::String(to)]) => {
+            Ok(Value::String(s.replace(from.as_str(), to)))
+        }
+        (Value::String(s), "split", [Value::String(sep)]) => {
+            Ok(Value::array(s.split(sep.as_str()).map(|part| Value::String(part.to_string())).collect()))
+        }
+        (Value::String(s), "chars", []) => Ok(Value::array(s.chars().map(|c| Value::String(c.to_string())).collect())),
+
+        (Value::Array(elements), "push", [item]) => {
+            elements.borrow_mut().push(item.clone());
+            Ok(Value::Nil)
+        }
+        (Value::Array(elements), "pop", []) => {
+            elements.borrow_mut().pop().ok_or_else(|| "pop from an empty array".to_string())
+        }
+        (Value::Slice(_), "push" | "pop", _) => Err(format!("a slice cannot {}; it is a view of a fixed range", method)),
+        (Value::Array(_) | Value::Slice(_), _, _) => {
+            let slice = receiver.as_slice().expect("arrays and slices view as slices");
+            match (method, args.as_slice()) {
+                ("length", []) => Ok(Value::Number(slice.len as i64)),
+                ("index_of", [item]) => {
+                    let found = slice.elements().iter().position(|e| Interpreter::values_equal(e, item));
+                    Ok(Value::Number(found.map_or(-1, |i| i as i64)))
+                }
+                ("join", [Value::String(sep)]) => {
+                    let parts: Vec<String> = slice.elements().iter().map(|e| e.to_string()).collect();
+                    Ok(Value::String(parts.join(sep)))
+                }
+                // In place, also through a slice into the array it views
+                ("reverse", []) => {
+                    let mut elements = slice.elements();
+                    elements.reverse();
+                    write_back(&slice, elements);
+                    Ok(Value::Nil)
+                }
+                ("sort", []) => {
+                    let mut elements = slice.elements();
+                    let mut comparable = true;
+                    elements.sort_by(|a, b| {
+                        compare(a, b).unwrap_or_else(|| {
+                            comparable = false;
+                            Ordering::Equal
+                        })
+                    });
+                    if !comparable {
+                        return Err("sort needs all numbers or all strings".to_string());
+                    }
+                    write_back(&slice, elements);
+                    Ok(Value::Nil)
+                }
+                _ => delegate(receiver, method, args),
+            }
+        }
+
+        (Value::Map(entries), "length", []) => Ok(Value::Number(entries.borrow().len() as i64)),
+        (Value::Map(entries), "get", [key, default]) => {
+            let key = Key::from_value(key)?;
+            Ok(entries.borrow().get(&key).cloned().unwrap_or_else(|| default.clone()))
+        }
+        (Value::Set(elements), "length", []) => Ok(Value::Number(elements.borrow().len() as i64)),
+        _ => delegate(receiver, method, args),
+    }
+}
+
+// `c.contains(x)`, `m.keys()` and the like, as the free functions of the same name
+fn delegate(receiver: &Value, method: &str, mut args: Vec<Value>) -> Result<Value, String> {
+    let applies = matches!(
+        (receiver, method),
+        (Value::Map(_), "keys" | "values" | "remove")
+            | (Value::Set(_), "insert" | "remove")
+            | (Value::Map(_) | Value::Set(_) | Value::Array(_) | Value::Slice(_) | Value::String(_), "contains")
+    );
+    if !applies {
+        let type_name = match receiver {
+            Value::Struct(instance) => instance.borrow().name.clone(),
+            other => other.type_name().to_string(),
+        };
+        return Err(format!("{} has no method '{}' taking ({})", type_name, method, type_names(&args)));
+    }
+    args.insert(0, receiver.clone());
+    call(method, args).map_err(|e| format!("{}: {}", method, e))
+}
+
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ if NumericType::of(a).is_some() && NumericType::of(b).is_some() => numeric::compare(a, b),
+        _ => None,
+    }
+}
+
+// Stores `elements` back into the positions the slice views
+fn write_back(slice: &ArraySlice, elements: Vec<Value>) {
+    let mut data = slice.data.borrow_mut();
+    for (i, element) in elements.into_iter().enumerate() {
+        if let Some(target) = data.get_mut(slice.position(i)) {
+            *target = element;
+        }
+    }
+}
+
+fn contains(container: &Value, item: &Value) -> Result<bool, String> {
+    match container {
+        // Keys that could never be stored are simply absent
+        Value::Map(entries) => Ok(Key::from_value(item).is_ok_and(|key| entries.borrow().contains_key(&key))),
+        Value::Set(elements) => Ok(Key::from_value(item).is_ok_and(|key| elements.borrow().contains(&key))),
+        Value::String(s) => match item {
+            Value::String(sub) => Ok(s.contains(sub.as_str())),
+            other => Err(format!("cannot search a String for {}", other.type_name())),
+        },
+        other => match other.as_slice() {
+            Some(slice) => Ok(slice.elements().iter().any(|e| Interpreter::values_equal(e, item))),
+            None => Err(format!("{} is not a collection", other.type_name())),
+        },
+    }
+}
+
+fn type_names(args: &[Value]) -> String {
+    args.iter().map(Value::type_name).collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,424 @@
+// || shree ganesh ||
+// Static checks over the parsed program, run before anything executes.
+// For now: every `match` must cover all values its subject can have.
+
+use crate::ast::{Expr, ExprKind, MatchArm, Pattern, PatternKind, Stmt, StmtKind, StringSegment};
+use crate::token::Span;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+// A pattern with its names resolved against the declared enums
+#[derive(Debug, Clone)]
+enum Pat {
+    Any,
+    Variant { enum_name: String, variant: String, fields: Vec<Pat> },
+    // A tuple, or a struct with all its fields in declaration order
+    Product { struct_name: Option<String>, fields: Vec<Pat> },
+    Boolean(bool),
+    Other, // any other literal, range or array: never covers a whole type
+}
+
+// Keeps the declarations seen so far, so the REPL can check one line at a time
+pub struct Checker {
+    enums: HashMap<String, Vec<(String, usize)>>, // variant names and payload sizes
+    structs: HashMap<String, Vec<String>>,        // field names
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Checker {
+            enums: HashMap::new(),
+            structs: HashMap::new(),
+        }
+    }
+
+    pub fn check(&mut self, program: &[Stmt]) -> Vec<CheckError> {
+        // Declarations first, so a match may come before the enum it uses
+        for stmt in program {
+            self.declare(stmt);
+        }
+        let mut errors = Vec::new();
+        for stmt in program {
+            self.check_stmt(stmt, &mut errors);
+        }
+        errors
+    }
+
+    fn declare(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Enum { name, variants, .. } => {
+                let variants = variants.iter().map(|(variant, payload)| (variant.clone(), payload.len())).collect();
+                self.enums.insert(name.clone(), variants);
+            }
+            StmtKind::Struct { name, fields, .. } => {
+                self.structs.insert(name.clone(), fields.iter().map(|(field, _)| field.clone()).collect());
+            }
+            _ => Self::child_stmts(stmt).into_iter().for_each(|child| self.declare(child)),
+        }
+    }
+
+    fn check_stmt(&self, stmt: &Stmt, errors: &mut Vec<CheckError>) {
+        match &stmt.kind {
+            StmtKind::Let { value, .. } | StmtKind::LetPattern { value, .. } => self.check_expr(value, errors),
+            StmtKind::ExprStmt(expr) | StmtKind::Return(expr) => self.check_expr(expr, errors),
+            StmtKind::IfStmt { condition, .. } | StmtKind::While { condition, .. } => self.check_expr(condition, errors),
+            StmtKind::For { range, .. } | StmtKind::Parallel { range, .. } => self.check_expr(range, errors),
+            StmtKind::Branch { arms, .. } => arms.iter().for_each(|(condition, _)| self.check_expr(condition, errors)),
+            _ => {}
+        }
+        for child in Self::child_stmts(stmt) {
+            self.check_stmt(child, errors);
+        }
+    }
+
+    // The statements nested directly in a statement
+    fn child_stmts(stmt: &Stmt) -> Vec<&Stmt> {
+        match &stmt.kind {
+            StmtKind::Block(statements) | StmtKind::Impl { methods: statements, .. } => statements.iter().collect(),
+            StmtKind::IfStmt { then_branch, else_branch, .. } => {
+                std::iter::once(then_branch.as_ref()).chain(else_branch.as_deref()).collect()
+            }
+            StmtKind::FunctionDef { body, .. }
+            | StmtKind::For { body, .. }
+            | StmtKind::While { body, .. }
+            | StmtKind::Parallel { body, .. } => vec![body.as_ref()],
+            StmtKind::Branch { arms, fallback } => arms.iter().map(|(_, body)| body).chain(fallback.as_deref()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn check_expr(&self, expr: &Expr, errors: &mut Vec<CheckError>) {
+        match &expr.kind {
+            ExprKind::Match { subject, arms } => {
+                self.check_expr(subject, errors);
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        self.check_expr(guard, errors);
+                    }
+                    self.check_stmt(&arm.body, errors);
+                }
+                self.check_match(expr.span, arms, errors);
+            }
+            ExprKind::Unary { expr: inner, .. } | ExprKind::Grouping(inner) => self.check_expr(inner, errors),
+            ExprKind::Binary { left, right, .. } => {
+                self.check_expr(left, errors);
+                self.check_expr(right, errors);
+            }
+            ExprKind::Assignment { target, value } | ExprKind::CompoundAssignment { target, value, .. } => {
+                self.check_expr(target, errors);
+                self.check_expr(value, errors);
+            }
+            ExprKind::FunctionCall { callee, arguments } => {
+                self.check_expr(callee, errors);
+                arguments.iter().for_each(|arg| self.check_expr(arg, errors));
+            }
+            ExprKind::MethodCall { receiver, arguments, .. } => {
+                self.check_expr(receiver, errors);
+                arguments.iter().for_each(|arg| self.check_expr(arg, errors));
+            }
+            ExprKind::Range { start, end, step } => {
+                for bound in [start, end, step].into_iter().flatten() {
+                    self.check_expr(bound, errors);
+                }
+            }
+            ExprKind::Array(elements) | ExprKind::Set(elements) | ExprKind::Tuple(elements) => {
+                elements.iter().for_each(|element| self.check_expr(element, errors));
+            }
+            ExprKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.check_expr(key, errors);
+                    self.check_expr(value, errors);
+                }
+            }
+            ExprKind::Index { object, index } => {
+                self.check_expr(object, errors);
+                self.check_expr(index, errors);
+            }
+            ExprKind::Field { object, .. } => self.check_expr(object, errors),
+            ExprKind::Lambda { body, .. } => self.check_stmt(body, errors),
+            ExprKind::StructLiteral { fields, .. } => fields.iter().for_each(|(_, value)| self.check_expr(value, errors)),
+            ExprKind::InterpolatedString(segments) => {
+                for segment in segments {
+                    if let StringSegment::Expr(inner) = segment {
+                        self.check_expr(inner, errors);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Reports patterns naming unknown variants, and the first value no arm covers.
+    // Guarded arms may not match, so they do not count towards coverage.
+    fn check_match(&self, span: Span, arms: &[MatchArm], errors: &mut Vec<CheckError>) {
+        let mut rows = Vec::new();
+        let mut resolved = true;
+        for arm in arms {
+            match self.resolve(&arm.pattern) {
+                Ok(pat) if arm.guard.is_none() => rows.push(vec![pat]),
+                Ok(_) => {}
+                Err(e) => {
+                    errors.push(e);
+                    resolved = false;
+                }
+            }
+        }
+        if !resolved {
+            return;
+        }
+        if let Some(missing) = self.missing(&rows, 1) {
+            errors.push(CheckError {
+                message: format!("non-exhaustive match: `{}` is not covered", missing[0]),
+                span,
+            });
+        }
+    }
+
+    fn resolve(&self, pattern: &Pattern) -> Result<Pat, CheckError> {
+        let error = |message: String| CheckError { message, span: pattern.span };
+        match &pattern.kind {
+            PatternKind::Wildcard => Ok(Pat::Any),
+            // A bare name is a variant if some enum declares one by that name
+            PatternKind::Binding(name) if self.find_variant(None, name).is_some() => {
+                self.unambiguous(name).map_err(error)?;
+                let (enum_name, arity) = self.find_variant(None, name).unwrap_or_default();
+                if arity != 0 {
+                    return Err(error(format!("variant `{}` holds {} value(s), the pattern lists 0", name, arity)));
+                }
+                Ok(Pat::Variant { enum_name, variant: name.clone(), fields: Vec::new() })
+            }
+            PatternKind::Binding(_) => Ok(Pat::Any),
+            PatternKind::Literal(Expr { kind: ExprKind::Boolean(b), .. }) => Ok(Pat::Boolean(*b)),
+            PatternKind::Literal(_) | PatternKind::Range { .. } => Ok(Pat::Other),
+            PatternKind::Variant { enum_name, name, fields } => {
+                if enum_name.is_none() {
+                    self.unambiguous(name).map_err(error)?;
+                }
+                let (enum_name, arity) = self.find_variant(enum_name.as_deref(), name).ok_or_else(|| {
+                    error(match enum_name {
+                        Some(enum_name) if self.enums.contains_key(enum_name) => {
+                            format!("enum `{}` has no variant `{}`", enum_name, name)
+                        }
+                        Some(enum_name) => format!("unknown enum `{}`", enum_name),
+                        None => format!("unknown variant `{}`", name),
+                    })
+                })?;
+                if arity != fields.len() {
+                    return Err(error(format!("variant `{}` holds {} value(s), the pattern lists {}", name, arity, fields.len())));
+                }
+                let fields = fields.iter().map(|field| self.resolve(field)).collect::<Result<_, _>>()?;
+                Ok(Pat::Variant { enum_name, variant: name.clone(), fields })
+            }
+            PatternKind::Tuple(elements) => {
+                let fields = elements.iter().map(|element| self.resolve(element)).collect::<Result<_, _>>()?;
+                Ok(Pat::Product { struct_name: None, fields })
+            }
+            PatternKind::Array(elements) => {
+                for element in elements {
+                    self.resolve(element)?;
+                }
+                Ok(Pat::Other)
+            }
+            PatternKind::Struct { name, fields } => {
+                let declared = self.structs.get(name).ok_or_else(|| error(format!("unknown struct `{}`", name)))?;
+                if let Some((field, pattern)) = fields.iter().find(|(field, _)| !declared.contains(field)) {
+                    return Err(CheckError { message: format!("struct `{}` has no field `{}`", name, field), span: pattern.span });
+                }
+                // Fields left out of the pattern match anything
+                let mut resolved = Vec::with_capacity(declared.len());
+                for field in declared {
+                    resolved.push(match fields.iter().find(|(given, _)| given == field) {
+                        Some((_, pattern)) => self.resolve(pattern)?,
+                        None => Pat::Any,
+                    });
+                }
+                Ok(Pat::Product { struct_name: Some(name.clone()), fields: resolved })
+            }
+        }
+    }
+
+    // The enum declaring a variant and its payload size, searching all enums if the
+    // pattern does not name one
+    fn find_variant(&self, enum_name: Option<&str>, variant: &str) -> Option<(String, usize)> {
+        let mut candidates = self
+            .enums
+            .iter()
+            .filter(|(name, _)| enum_name.is_none_or(|wanted| wanted == name.as_str()));
+        candidates.find_map(|(name, variants)| {
+            variants
+                .iter()
+                .find(|(candidate, _)| candidate == variant)
+                .map(|(_, arity)| (name.clone(), *arity))
+        })
+    }
+
+    fn unambiguous(&self, variant: &str) -> Result<(), String> {
+        let mut owners: Vec<&String> = self
+            .enums
+            .iter()
+            .filter(|(_, variants)| variants.iter().any(|(name, _)| name == variant))
+            .map(|(name, _)| name)
+            .collect();
+        if owners.len() > 1 {
+            owners.sort();
+            return Err(format!("variant `{}` is ambiguous; write `{}.{}`", variant, owners[0], variant));
+        }
+        Ok(())
+    }
+
+    // A list of values, one per column, that no row matches; None if the rows cover
+    // everything. Columns of enum or boolean patterns are split per constructor, any
+    // other column is only covered by a wildcard or binding.
+    fn missing(&self, rows: &[Vec<Pat>], columns: usize) -> Option<Vec<String>> {
+        if columns == 0 {
+            return if rows.is_empty() { Some(Vec::new()) } else { None };
+        }
+
+        let first_enum = rows.iter().find_map(|row| match &row[0] {
+            Pat::Variant { enum_name, .. } => Some(enum_name.clone()),
+            _ => None,
+        });
+        if let Some(enum_name) = first_enum {
+            for (variant, arity) in self.enums.get(&enum_name).into_iter().flatten() {
+                let specialized: Vec<Vec<Pat>> = rows
+                    .iter()
+                    .filter_map(|row| {
+                        let head = match &row[0] {
+                            Pat::Variant { enum_name: e, variant: v, fields } if *e == enum_name && v == variant => fields.clone(),
+                            Pat::Any => vec![Pat::Any; *arity],
+                            _ => return None,
+                        };
+                        Some(head.into_iter().chain(row[1..].iter().cloned()).collect())
+                    })
+                    .collect();
+                if let Some(mut witness) = self.missing(&specialized, arity + columns - 1) {
+                    let rest = witness.split_off(*arity);
+                    let shown = if *arity == 0 {
+                        format!("{}.{}", enum_name, variant)
+                    } else {
+                        format!("{}.{}({})", enum_name, variant, witness.join(", "))
+                    };
+                    return Some(std::iter::once(shown).chain(rest).collect());
+                }
+            }
+            return None;
+        }
+
+        // Tuples and structs have a single shape, so only their fields need covering
+        let product = rows.iter().find_map(|row| match &row[0] {
+            Pat::Product { struct_name, fields } => Some((struct_name.clone(), fields.len())),
+            _ => None,
+        });
+        if let Some((struct_name, arity)) = product {
+            let specialized: Vec<Vec<Pat>> = rows
+                .iter()
+                .filter_map(|row| {
+                    let head = match &row[0] {
+                        Pat::Product { fields, .. } if fields.len() == arity => fields.clone(),
+                        Pat::Any => vec![Pat::Any; arity],
+                        _ => return None,
+                    };
+                    Some(head.into_iter().chain(row[1..].iter().cloned()).collect())
+                })
+                .collect();
+            return self.missing(&specialized, arity + columns - 1).map(|mut witness| {
+                let rest = witness.split_off(arity);
+                let shown = match &struct_name {
+                    Some(name) => {
+                        let names = self.structs.get(name).cloned().unwrap_or_default();
+                        let fields: Vec<String> = names.iter().zip(&witness).map(|(f, w)| format!("{}: {}", f, w)).collect();
+                        format!("{} {{ {} }}", name, fields.join(", "))
+                    }
+                    None if arity == 1 => format!("({},)", witness[0]),
+                    None => format!("({})", witness.join(", ")),
+                };
+                std::iter::once(shown).chain(rest).collect()
+            });
+        }
+
+        if rows.iter().any(|row| matches!(row[0], Pat::Boolean(_))) {
+            for value in [true, false] {
+                let specialized: Vec<Vec<Pat>> = rows
+                    .iter()
+                    .filter(|row| matches!(row[0], Pat::Any) || matches!(row[0], Pat::Boolean(b) if b == value))
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                if let Some(witness) = self.missing(&specialized, columns - 1) {
+                    return Some(std::iter::once(value.to_string()).chain(witness).collect());
+                }
+            }
+            return None;
+        }
+
+        let defaults: Vec<Vec<Pat>> = rows
+            .iter()
+            .filter(|row| matches!(row[0], Pat::Any))
+            .map(|row| row[1..].to_vec())
+            .collect();
+        self.missing(&defaults, columns - 1)
+            .map(|witness| std::iter::once("_".to_string()).chain(witness).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn errors(source: &str) -> Vec<String> {
+        let tokens = Lexer::new(source).tokenize().expect("source should lex");
+        let program = Parser::new(tokens).parse().expect("source should parse");
+        Checker::new().check(&program).into_iter().map(|e| e.message).collect()
+    }
+
+    const SHAPE: &str = "enum Shape: Circle(Float), Rect(Float, Float), Empty\nlet s = Shape.Empty\n";
+
+    #[test]
+    fn every_variant_covered_is_exhaustive() {
+        let source = format!("{}match s:\n    Circle(r) => print(r)\n    Rect(w, _) => print(w)\n    Empty => print(0)\n", SHAPE);
+        assert_eq!(errors(&source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn a_missing_variant_is_named() {
+        let source = format!("{}match s:\n    Circle(r) => print(r)\n    Empty => print(0)\n", SHAPE);
+        assert_eq!(errors(&source), vec!["non-exhaustive match: `Shape.Rect(_, _)` is not covered"]);
+    }
+
+    #[test]
+    fn guarded_arms_cover_nothing() {
+        let source = format!("{}match s:\n    Circle(r) if r > 1.0 => print(r)\n    Rect(w, h) => print(w)\n    Empty => print(0)\n", SHAPE);
+        assert_eq!(errors(&source), vec!["non-exhaustive match: `Shape.Circle(_)` is not covered"]);
+    }
+
+    #[test]
+    fn numbers_need_a_wildcard() {
+        assert_eq!(errors("match 3:\n    0 => print(0)\n    1..10 => print(1)\n"), vec!["non-exhaustive match: `_` is not covered"]);
+        assert_eq!(errors("match 3:\n    0 => print(0)\n    n => print(n)\n"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unknown_variants_are_reported() {
+        let source = format!("{}match s:\n    Shape.Triangle => print(3)\n    _ => print(0)\n", SHAPE);
+        assert_eq!(errors(&source), vec!["enum `Shape` has no variant `Triangle`"]);
+    }
+
+    #[test]
+    fn tuples_are_covered_column_by_column() {
+        let source = "let p = (true, false)\nmatch p:\n    (true, _) => print(1)\n    (false, true) => print(2)\n";
+        assert_eq!(errors(source), vec!["non-exhaustive match: `(false, false)` is not covered"]);
+    }
+}
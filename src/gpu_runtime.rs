@@ -1,15 +1,19 @@
 // || shree ganesh ||
 // GPURuntime handles registration and execution of GPU-accelerated code
 
-use crate::ast::{Expr, Stmt};
+use crate::ast::{Expr, ExprKind, Stmt};
 use crate::interpreter::Value;
 use std::collections::HashMap;
 use std::thread;
 use std::time::Duration;
 
+// (param name, param type) pairs and the body of a registered kernel
+type KernelDef = (Vec<(String, String)>, Box<Stmt>);
+
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct GPURuntime {
-    functions: HashMap<String, (Vec<(String, String)>, Box<Stmt>)>, // name => (params, body)
+    functions: HashMap<String, KernelDef>, // name => (params, body)
     available: bool,
     parallel_threads: usize,
 }
@@ -41,7 +45,7 @@ impl GPURuntime {
         &mut self,
         name: String,
         params: Vec<(String, String)>,
-        body: &Box<Stmt>,
+        body: &Stmt,
     ) -> Result<(), String> {
         println!("[GPU] Registering GPU function: {}", name);
         println!("[GPU] Function parameters: {:?}", params);
@@ -49,7 +53,7 @@ impl GPURuntime {
         // In a real implementation, we would compile the function to GPU code here
         println!("[GPU] Simulating JIT compilation for function");
         
-        self.functions.insert(name.clone(), (params, body.clone()));
+        self.functions.insert(name.clone(), (params, Box::new(body.clone())));
         println!("[GPU] Successfully registered function: {}", name);
         
         Ok(())
@@ -57,16 +61,16 @@ impl GPURuntime {
 
     pub fn execute_parallel(
         &mut self,
-        var: &String,
+        var: &str,
         range_expr: &Expr,
-        _body: &Box<Stmt>,
+        _body: &Stmt,
     ) -> Result<(), String> {
         // In real CUDA, you'd translate and compile kernels.
         // Here we just simulate loop body execution in parallel
         println!("[GPU] Executing parallel loop with variable '{}'", var);
         
-        match range_expr {
-            Expr::Number(n) => {
+        match &range_expr.kind {
+            ExprKind::Number(n) => {
                 let n = *n;
                 println!("[GPU] Simulating parallel loop over range 0..{}", n);
                 println!("[GPU] Allocating {} threads on simulated GPU", self.parallel_threads);
@@ -75,7 +79,7 @@ impl GPURuntime {
                 let handles: Vec<_> = (0..self.parallel_threads.min(n as usize))
                     .map(|thread_id| {
                         // Calculate the range of iterations this thread handles
-                        let chunk_size = (n as usize + self.parallel_threads - 1) / self.parallel_threads;
+                        let chunk_size = (n as usize).div_ceil(self.parallel_threads);
                         let start = thread_id * chunk_size;
                         let end = std::cmp::min(start + chunk_size, n as usize);
                         
@@ -101,19 +105,26 @@ impl GPURuntime {
                 println!("[GPU] Parallel execution complete");
                 Ok(())
             },
-            Expr::Range { start, end } => {
-                match (&**start, &**end) {
-                    (Expr::Number(start_val), Expr::Number(end_val)) => {
-                        println!("[GPU] Simulating parallel loop over range {}..{}", start_val, end_val);
+            ExprKind::Range { start: Some(start), end: Some(end), step } => {
+                let step_val = match step.as_deref().map(|step| &step.kind) {
+                    None => 1,
+                    Some(ExprKind::Number(n)) if *n > 0 => *n,
+                    Some(_) => return Err("GPU parallel loop step must be a positive numeric literal".to_string()),
+                };
+                match (&start.kind, &end.kind) {
+                    (ExprKind::Number(start_val), ExprKind::Number(end_val)) => {
+                        println!("[GPU] Simulating parallel loop over range {}..{} step {}", start_val, end_val, step_val);
                         
-                        let range_size = end_val - start_val;
+                        // Widened, as the bounds may be a whole Int range apart
+                        let range_size = *end_val as i128 - *start_val as i128;
                         if range_size <= 0 {
                             println!("[GPU] Empty or invalid range, nothing to execute");
                             return Ok(());
                         }
                         
-                        println!("[GPU] Executing loop body {} times in parallel", range_size);
-                        for i in *start_val..*end_val {
+                        let lanes = (range_size as u128).div_ceil(step_val as u128);
+                        println!("[GPU] Executing loop body {} times in parallel", lanes);
+                        for i in (*start_val..*end_val).step_by(step_val as usize) {
                             println!("[GPU::Sim] Iteration {i}");
                         }
                         Ok(())
@@ -125,18 +136,16 @@ impl GPURuntime {
         }
     }
 
-    pub fn execute_function(&self, name: &str, args: Vec<Value>) -> Result<Value, String> {
+    // Simulates launching a registered kernel with `args`; the interpreter runs its body
+    pub fn launch_function(&self, name: &str, args: &[Value]) -> Result<(), String> {
         // Check if function is GPU-registered
-        if !self.functions.contains_key(name) {
+        let Some((params, _body)) = self.functions.get(name) else {
             return Err(format!("GPU function '{}' not found", name));
-        }
+        };
 
         println!("[GPU] Executing function: {}", name);
         println!("[GPU] Arguments: {:?}", args);
         
-        // Get the function definition
-        let (params, _body) = self.functions.get(name).unwrap();
-        
         // Check if argument count matches parameter count
         if args.len() != params.len() {
             return Err(format!(
@@ -152,34 +161,6 @@ impl GPURuntime {
         thread::sleep(Duration::from_millis(100));
         
         println!("[GPU] Executing kernel");
-        thread::sleep(Duration::from_millis(50));
-        
-        println!("[GPU] Kernel execution complete");
-        println!("[GPU] Transferring results back from GPU memory");
-        
-        // In a real implementation, we would execute the function on the GPU
-        // and return the actual result
-        
-        // For now, just return a dummy result
-        match name {
-            "add_gpu" => {
-                if args.len() >= 2 {
-                    match (&args[0], &args[1]) {
-                        (Value::Number(a), Value::Number(b)) => {
-                            let result = a + b;
-                            println!("[GPU] Function returned: {}", result);
-                            return Ok(Value::Number(result));
-                        },
-                        _ => {}
-                    }
-                }
-                println!("[GPU] Function returned default value");
-                Ok(Value::Number(42)) // Default result
-            },
-            _ => {
-                println!("[GPU] Function returned nil");
-                Ok(Value::Nil)
-            }
-        }
+        Ok(())
     }
 }
@@ -1,11 +1,14 @@
 // || shree ganesh ||
 // Final Vortex Interpreter with return support and GPU placeholder
 
-use crate::ast::{Expr, Stmt};
+use crate::ast::{Expr, ExprKind, MatchArm, Param, Pattern, PatternKind, Stmt, StmtKind, StringSegment};
+use crate::builtins;
 use crate::gpu_runtime::GPURuntime;
-use crate::token::Token;
+use crate::numeric::{self, NumericType};
+use crate::token::{Span, Token};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
 use std::rc::Rc;
 
@@ -13,36 +16,385 @@ use std::rc::Rc;
 pub enum RuntimeResult {
     Value(Value),
     Return(Value),
-    None,
+    Break(Option<String>),    // unwinds to the loop with this label, or the innermost one
+    Continue(Option<String>),
+}
+
+// Evaluated `start..end step n`; bounds the source leaves out are `None`
+struct RangeBounds {
+    start: Option<i64>,
+    end: Option<i64>,
+    step: i64,
+}
+
+// What a loop does after one run of its body
+enum LoopFlow {
+    Next,
+    Exit,
+    Propagate(RuntimeResult), // a `return`, or a `break`/`continue` aimed at an outer loop
+}
+
+// One lane of a `parallel` loop: its own scope, and whether it has ended
+struct Lane {
+    environment: Rc<RefCell<Environment>>,
+    done: bool,
+}
+
+// An error raised while running a program. The span is filled in by the innermost
+// statement or expression that failed, so callers can print `file.vx:line:col`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl RuntimeError {
+    // Attach a location unless a more precise one was already recorded
+    fn at(mut self, span: Span) -> Self {
+        if self.span.is_none() {
+            self.span = Some(span);
+        }
+        self
+    }
+}
+
+impl From<String> for RuntimeError {
+    fn from(message: String) -> Self {
+        RuntimeError { message, span: None }
+    }
+}
+
+impl From<&str> for RuntimeError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{}: {}", span, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Number(i64),
     Floating(f64),
+    // Sized numbers for GPU work; see `numeric` for their arithmetic
+    Int32(i32),
+    UInt32(u32),
+    Float32(f32),
     String(String),
     Boolean(bool),
     Nil,
-    // Function(name, param_names, body, is_gpu)
-    Function(String, Vec<String>, Box<crate::ast::Stmt>, bool),
+    Function(Rc<Function>),
+    Builtin(String), // `print` and the functions in `builtins`, used as values
+    // Arrays are shared by reference: copies of the value see each other's writes
+    Array(Rc<RefCell<Vec<Value>>>),
+    // `a[2..8 step 2]`: a window onto an array, sharing its storage
+    Slice(ArraySlice),
+    // Maps and sets are shared by reference like arrays
+    Map(Rc<RefCell<BTreeMap<Key, Value>>>),
+    Set(Rc<RefCell<BTreeSet<Key>>>),
+    // An instance of a `struct` declaration, also shared by reference
+    Struct(Rc<RefCell<StructValue>>),
+    // A variant of an `enum` declaration; immutable, so sharing is invisible
+    Variant(Rc<VariantValue>),
+    Tuple(Rc<Vec<Value>>), // immutable like variants
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantValue {
+    pub enum_name: String,
+    pub variant: String,
+    pub payload: Vec<Value>,
+}
+
+// A named or anonymous function and the scope it was defined in. Calls run in a new
+// scope inside that one, so functions see the variables around their definition
+// (lexical scoping), also after the defining call has returned.
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub body: Stmt,
+    pub gpu: bool,
+    pub closure: Rc<RefCell<Environment>>,
+}
+
+// Functions are equal only to themselves
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// The captured scope is left out: it usually contains the function itself
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Function").field("name", &self.name).field("params", &self.params).finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructValue {
+    pub name: String,
+    pub fields: Vec<(String, Value)>, // field values in declaration order
+}
+
+// A map key or set element. Only values with a total order qualify, which also makes
+// iteration deterministic: keys are visited in sorted order (booleans, then integers,
+// then strings).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Key {
+    Boolean(bool),
+    Number(i64),
+    String(String),
+}
+
+impl Key {
+    pub fn from_value(value: &Value) -> Result<Key, String> {
+        match value {
+            Value::Boolean(b) => Ok(Key::Boolean(*b)),
+            Value::Number(n) => Ok(Key::Number(*n)),
+            // Sized integers are stored as plain Ints, so `m[1i32]` and `m[1]` are one key
+            Value::Int32(_) | Value::UInt32(_) => Ok(Key::Number(numeric::as_i64(value).unwrap_or_default())),
+            Value::String(s) => Ok(Key::String(s.clone())),
+            other => Err(format!("{} cannot be used as a map key or set element", other.type_name())),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            Key::Boolean(b) => Value::Boolean(*b),
+            Key::Number(n) => Value::Number(*n),
+            Key::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+// `len` elements of an array, starting at `start` and `step` positions apart. Reads and
+// writes go straight to the underlying array. A whole array is viewed as the slice with
+// start 0 and step 1.
+#[derive(Debug, Clone)]
+pub struct ArraySlice {
+    pub(crate) data: Rc<RefCell<Vec<Value>>>,
+    start: usize,
+    pub(crate) len: usize,
+    step: usize,
+}
+
+impl ArraySlice {
+    // Position in the underlying array of the slice's `i`th element (`i` < `len`)
+    pub(crate) fn position(&self, i: usize) -> usize {
+        self.start + i * self.step
+    }
+
+    // Position of element `index`, or an error if it is outside the slice. Also catches
+    // slices left pointing past the end of an array that has since shrunk.
+    pub(crate) fn checked_position(&self, index: &Value) -> Result<usize, String> {
+        let i = match numeric::as_i64(index) {
+            Some(i) if i >= 0 && (i as u64) < self.len as u64 => i as usize,
+            Some(i) => return Err(format!("Index {} out of bounds for length {}", i, self.len)),
+            None => return Err(format!("Index must be an Int, not {}", index.type_name())),
+        };
+        let position = self.position(i);
+        if position < self.data.borrow().len() {
+            Ok(position)
+        } else {
+            Err(format!("Index {} refers past the end of the array this slice was taken from", i))
+        }
+    }
+
+    // A copy of the elements currently visible through the slice
+    pub(crate) fn elements(&self) -> Vec<Value> {
+        let data = self.data.borrow();
+        (0..self.len).filter_map(|i| data.get(self.position(i)).cloned()).collect()
+    }
+}
+
+// Slices are equal when they show equal elements, wherever those are stored
+impl PartialEq for ArraySlice {
+    fn eq(&self, other: &Self) -> bool {
+        self.elements() == other.elements()
+    }
+}
+
+impl Value {
+    pub fn array(elements: Vec<Value>) -> Value {
+        Value::Array(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn map(entries: BTreeMap<Key, Value>) -> Value {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn set(elements: BTreeSet<Key>) -> Value {
+        Value::Set(Rc::new(RefCell::new(elements)))
+    }
+
+    // Arrays and slices as a slice, for code that handles both alike
+    pub(crate) fn as_slice(&self) -> Option<ArraySlice> {
+        match self {
+            Value::Array(data) => Some(ArraySlice {
+                data: Rc::clone(data),
+                start: 0,
+                len: data.borrow().len(),
+                step: 1,
+            }),
+            Value::Slice(slice) => Some(slice.clone()),
+            _ => None,
+        }
+    }
+
+    // Name of the value's type, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "Int",
+            Value::Floating(_) => "Float",
+            Value::Int32(_) => "Int32",
+            Value::UInt32(_) => "UInt32",
+            Value::Float32(_) => "Float32",
+            Value::String(_) => "String",
+            Value::Boolean(_) => "Boolean",
+            Value::Nil => "Nil",
+            Value::Function(_) | Value::Builtin(_) => "Function",
+            Value::Array(_) => "Array",
+            Value::Slice(_) => "Slice",
+            Value::Map(_) => "Map",
+            Value::Set(_) => "Set",
+            Value::Struct(_) => "Struct",
+            Value::Variant(_) => "Enum",
+            Value::Tuple(_) => "Tuple",
+        }
+    }
+}
+
+impl Value {
+    // How the value is shown inside a collection: strings are quoted there
+    fn fmt_nested(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{:?}", s),
+            other => write!(f, "{}", other),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_value().fmt_nested(f)
+    }
 }
 
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Value::Number(n) => write!(f, "{}", n),
+            // `2.0`, not `2`, so a Float never reads as an Int
+            Value::Floating(n) if n.is_finite() && n.fract() == 0.0 => write!(f, "{:.1}", n),
             Value::Floating(n) => write!(f, "{}", n),
+            Value::Int32(n) => write!(f, "{}", n),
+            Value::UInt32(n) => write!(f, "{}", n),
+            Value::Float32(n) if n.is_finite() && n.fract() == 0.0 => write!(f, "{:.1}", n),
+            Value::Float32(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "{}", s),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Nil => write!(f, "nil"),
-            Value::Function(name, ..) => write!(f, "<function {}>", name),
+            Value::Function(function) => write!(f, "<function {}>", function.name),
+            Value::Builtin(name) => write!(f, "<built-in function {}>", name),
+            Value::Array(_) | Value::Slice(_) => {
+                let elements = self.as_slice().map(|slice| slice.elements()).unwrap_or_default();
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_nested(f)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", key)?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Struct(instance) => {
+                let instance = instance.borrow();
+                write!(f, "{} {{ ", instance.name)?;
+                for (i, (field, value)) in instance.fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", field)?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, " }}")
+            }
+            Value::Variant(value) => {
+                write!(f, "{}.{}", value.enum_name, value.variant)?;
+                if !value.payload.is_empty() {
+                    write!(f, "(")?;
+                    for (i, field) in value.payload.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        field.fmt_nested(f)?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Value::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_nested(f)?;
+                }
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Value::Set(elements) if elements.borrow().is_empty() => write!(f, "set()"),
+            Value::Set(elements) => {
+                write!(f, "{{")?;
+                for (i, element) in elements.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
 
+// Where an assignment writes to
+enum Place {
+    Variable(String),
+    // Position in an array's storage, and the index expression it came from. It was in bounds
+    // when resolved, but evaluating the assigned value can shrink the array, so every access
+    // checks again.
+    Index(Rc<RefCell<Vec<Value>>>, usize, Span),
+    MapEntry(Rc<RefCell<BTreeMap<Key, Value>>>, Key),
+    Field(Rc<RefCell<StructValue>>, usize), // position of an existing field
+}
+
 #[derive(Clone)]
 pub struct Environment {
     values: HashMap<String, Value>,
+    mutable: HashSet<String>, // the names declared with `let mut`; all others are read-only
     parent: Option<Rc<RefCell<Environment>>>,
 }
 
@@ -50,28 +402,29 @@ impl Environment {
     pub fn new() -> Rc<RefCell<Self>> {
         let mut env = Environment {
             values: HashMap::new(),
+            mutable: HashSet::new(),
             parent: None,
         };
-        env.define(
-            "print".to_string(),
-            Value::Function(
-                "print".to_string(),
-                vec!["value".to_string()],
-                Box::new(crate::ast::Stmt::Block(vec![])), // dummy body
-                false, // not a GPU function
-            ),
-        );
+        env.define("print".to_string(), Value::Builtin("print".to_string()));
         Rc::new(RefCell::new(env))
     }
 
     pub fn with_parent(parent: Rc<RefCell<Environment>>) -> Rc<RefCell<Self>> {
         Rc::new(RefCell::new(Environment {
             values: HashMap::new(),
+            mutable: HashSet::new(),
             parent: Some(parent),
         }))
     }
 
+    // Defines a binding that cannot be assigned to; it may still be shadowed
     pub fn define(&mut self, name: String, value: Value) {
+        self.mutable.remove(&name);
+        self.values.insert(name, value);
+    }
+
+    pub fn define_mutable(&mut self, name: String, value: Value) {
+        self.mutable.insert(name.clone());
         self.values.insert(name, value);
     }
 
@@ -84,6 +437,9 @@ impl Environment {
 
     pub fn assign(&mut self, name: &str, value: Value) -> Result<(), String> {
         if self.values.contains_key(name) {
+            if !self.mutable.contains(name) {
+                return Err(format!("Cannot assign to immutable variable '{}'; only `let mut` variables can be assigned", name));
+            }
             self.values.insert(name.to_string(), value);
             Ok(())
         } else if let Some(parent) = &self.parent {
@@ -97,6 +453,12 @@ impl Environment {
 pub struct Interpreter {
     environment: Rc<RefCell<Environment>>,
     pub gpu_runtime: GPURuntime,
+    docs: HashMap<String, String>, // `///` documentation of declared functions and variables
+    structs: HashMap<String, Vec<(String, String)>>, // declared structs: field names and types
+    methods: HashMap<String, HashMap<String, Value>>, // methods from `impl` blocks, per type name
+    enums: HashMap<String, Vec<(String, Vec<String>)>>, // declared enums: variants and payload types
+    parallel_depth: usize,         // > 0 while running the body of a `parallel` loop
+    output: Option<String>,        // what `print` writes, where there is no stdout to print to
 }
 
 impl Interpreter {
@@ -113,10 +475,41 @@ impl Interpreter {
         Self {
             environment: Environment::new(),
             gpu_runtime,
+            docs: HashMap::new(),
+            structs: HashMap::new(),
+            methods: HashMap::new(),
+            enums: HashMap::new(),
+            parallel_depth: 0,
+            output: None,
         }
     }
 
-    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), String> {
+    // Makes `print` write to a buffer instead of stdout, for the browser REPL, which builds
+    // these sources into vortex-lang-wasm
+    #[allow(dead_code)]
+    pub fn capture_output(&mut self) {
+        self.output.get_or_insert_with(String::new);
+    }
+
+    // What `print` has written since the last call, when output is captured
+    #[allow(dead_code)]
+    pub fn take_output(&mut self) -> String {
+        self.output.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // Documentation attached to the most recent declaration of `name`, if any
+    pub fn doc(&self, name: &str) -> Option<&str> {
+        self.docs.get(name).map(|doc| doc.as_str())
+    }
+
+    fn record_doc(&mut self, name: &str, doc: &Option<String>) {
+        match doc {
+            Some(doc) => self.docs.insert(name.to_string(), doc.clone()),
+            None => self.docs.remove(name),
+        };
+    }
+
+    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), RuntimeError> {
         for stmt in statements {
             let result = self.execute(&stmt)?;
             if let RuntimeResult::Return(_) = result {
@@ -126,37 +519,41 @@ impl Interpreter {
         Ok(())
     }
 
-    fn execute(&mut self, stmt: &Stmt) -> Result<RuntimeResult, String> {
-        match stmt {
-            Stmt::ExprStmt(expr) => self.evaluate(expr),
-            Stmt::Let { name, value, .. } => {
-                match self.evaluate(value) {
-                    Ok(RuntimeResult::Value(v)) => {
-                        self.environment.borrow_mut().define(name.clone(), v);
-                        Ok(RuntimeResult::Value(Value::Nil))
-                    },
-                    Ok(RuntimeResult::Return(_)) => {
-                        Err("Invalid return in variable declaration".to_string())
-                    },
-                    Ok(RuntimeResult::None) => {
-                        // Define variable with default nil value
-                        self.environment.borrow_mut().define(name.clone(), Value::Nil);
-                        Ok(RuntimeResult::Value(Value::Nil))
-                    },
-                    Err(e) => {
-                        println!("Warning: Error in variable declaration: {}", e);
-                        // Define variable with default nil value to continue execution
-                        self.environment.borrow_mut().define(name.clone(), Value::Nil);
-                        Ok(RuntimeResult::Value(Value::Nil))
-                    }
+    fn execute(&mut self, stmt: &Stmt) -> Result<RuntimeResult, RuntimeError> {
+        self.execute_stmt(stmt).map_err(|e| e.at(stmt.span))
+    }
+
+    fn execute_stmt(&mut self, stmt: &Stmt) -> Result<RuntimeResult, RuntimeError> {
+        match &stmt.kind {
+            StmtKind::ExprStmt(expr) => self.evaluate(expr),
+            StmtKind::LetPattern { pattern, value, mutable } => {
+                let value = self.evaluate_value(value, "variable declaration")?;
+                self.bind_pattern(pattern, value, *mutable)?;
+                Ok(RuntimeResult::Value(Value::Nil))
+            }
+            StmtKind::Let { name, type_name, value, mutable, doc } => {
+                self.record_doc(name, doc);
+                let v = self.evaluate_value(value, "variable declaration")?;
+                let v = Self::annotated(v, type_name).map_err(|e| RuntimeError::from(e).at(value.span))?;
+                if *mutable {
+                    self.environment.borrow_mut().define_mutable(name.clone(), v);
+                } else {
+                    self.environment.borrow_mut().define(name.clone(), v);
                 }
+                Ok(RuntimeResult::Value(Value::Nil))
             }
-            Stmt::Block(statements) => {
+            StmtKind::Block(statements) => {
                 let previous = Rc::clone(&self.environment);
                 self.environment = Environment::with_parent(previous.clone());
                 for stmt in statements {
-                    let result = self.execute(stmt)?;
-                    if let RuntimeResult::Return(_) = result {
+                    let result = match self.execute(stmt) {
+                        Ok(result) => result,
+                        Err(e) => {
+                            self.environment = previous;
+                            return Err(e);
+                        }
+                    };
+                    if !matches!(result, RuntimeResult::Value(_)) {
                         self.environment = previous;
                         return Ok(result);
                     }
@@ -164,7 +561,7 @@ impl Interpreter {
                 self.environment = previous;
                 Ok(RuntimeResult::Value(Value::Nil))
             }
-            Stmt::IfStmt {
+            StmtKind::IfStmt {
                 condition,
                 then_branch,
                 else_branch,
@@ -179,50 +576,67 @@ impl Interpreter {
                         Ok(RuntimeResult::Value(Value::Nil))
                     }
                 } else {
-                    Err("Invalid return in if condition".to_string())
+                    Err("Invalid return in if condition".into())
                 }
             }
-            Stmt::Branch {
-                condition,
-                body,
-            } => {
-                println!("Executing branch statement");
-                let cond = self.evaluate(condition)?;
-                if let RuntimeResult::Value(val) = cond {
-                    if self.is_truthy(&val) {
-                        println!("Branch condition true, executing body");
-                        self.execute(body)
-                    } else {
-                        println!("Branch condition false, skipping body");
-                        Ok(RuntimeResult::Value(Value::Nil))
+            StmtKind::Branch { arms, fallback } => {
+                let taken = if self.parallel_depth > 0 {
+                    // Predicated like a GPU warp: the lane evaluates every condition, then
+                    // takes the first arm that holds for it
+                    let mut taken = None;
+                    for (i, (condition, _)) in arms.iter().enumerate() {
+                        let val = self.evaluate_value(condition, "branch condition")?;
+                        if taken.is_none() && self.is_truthy(&val) {
+                            taken = Some(i);
+                        }
                     }
+                    taken
                 } else {
-                    Err("Invalid return in branch condition".to_string())
+                    // First match: later conditions are not evaluated
+                    let mut taken = None;
+                    for (i, (condition, _)) in arms.iter().enumerate() {
+                        let val = self.evaluate_value(condition, "branch condition")?;
+                        if self.is_truthy(&val) {
+                            taken = Some(i);
+                            break;
+                        }
+                    }
+                    taken
+                };
+
+                match (taken, fallback) {
+                    (Some(i), _) => self.execute(&arms[i].1),
+                    (None, Some(fallback)) => self.execute(fallback),
+                    (None, None) => Ok(RuntimeResult::Value(Value::Nil)),
                 }
             }
-            Stmt::Fallback(body) => {
-                self.execute(body)
-            }
-            Stmt::For { var, range, body } => {
-                println!("Executing 'for' loop with variable '{}'", var);
+            StmtKind::For { pattern, range, body, label } => {
+                println!("Executing 'for' loop with variable '{}'", pattern);
                 
                 // Handle different range types
-                let (start_val, end_val) = match &range {
-                    Expr::Range { start, end } => {
-                        let s = match self.evaluate(start)? {
-                            RuntimeResult::Value(Value::Number(n)) => n,
-                            _ => 0,
-                        };
-                        let e = match self.evaluate(end)? {
-                            RuntimeResult::Value(Value::Number(n)) => n,
-                            _ => 0,
-                        };
-                        (s, e)
+                let items: Box<dyn Iterator<Item = Value>> = match &range.kind {
+                    ExprKind::Range { .. } => {
+                        let (s, e, step) = self.loop_bounds(range)?.unwrap_or((0, 0, 1));
+                        println!("Loop range: {}..{} step {}", s, e, step);
+                        Box::new((s..e).step_by(step as usize).map(Value::Number))
                     },
                     _ => {
-                        // Try to evaluate as a single number (0..n)
-                        match self.evaluate(&range)? {
-                            RuntimeResult::Value(Value::Number(count)) => (0, count),
+                        // Try to evaluate as a single number (0..n), an array or a slice
+                        match self.evaluate_value(range, "for loop range")? {
+                            count @ (Value::Number(_) | Value::Int32(_) | Value::UInt32(_)) => {
+                                Box::new((0..numeric::as_i64(&count).unwrap_or_default()).map(Value::Number))
+                            }
+                            // Iterates over the elements there were when the loop started
+                            sequence @ (Value::Array(_) | Value::Slice(_)) => {
+                                Box::new(sequence.as_slice().map(|slice| slice.elements()).unwrap_or_default().into_iter())
+                            }
+                            // Maps yield their keys and sets their elements, both in sorted order
+                            Value::Map(entries) => {
+                                Box::new(entries.borrow().keys().map(Key::to_value).collect::<Vec<_>>().into_iter())
+                            }
+                            Value::Set(elements) => {
+                                Box::new(elements.borrow().iter().map(Key::to_value).collect::<Vec<_>>().into_iter())
+                            }
                             _ => {
                                 println!("Warning: For loop range is not a valid range");
                                 return Ok(RuntimeResult::Value(Value::Nil));
@@ -230,131 +644,120 @@ impl Interpreter {
                         }
                     }
                 };
-                
-                println!("Loop range: {}..{}", start_val, end_val);
-                for i in start_val..end_val {
-                    println!("For loop iteration {}", i);
+
+                for item in items {
+                    println!("For loop iteration {}", item);
                     // Create a new environment for each iteration
                     let loop_env = Environment::with_parent(Rc::clone(&self.environment));
                     let prev_env = std::mem::replace(&mut self.environment, loop_env);
                     
-                    // Define the loop variable
-                    self.environment.borrow_mut().define(var.clone(), Value::Number(i));
-                    
-                    // Execute the loop body
-                    match self.execute(body) {
-                        Ok(RuntimeResult::Return(v)) => {
-                            println!("Return statement in loop body, exiting loop");
-                            self.environment = prev_env;
-                            return Ok(RuntimeResult::Return(v));
-                        },
-                        Err(e) => {
-                            println!("Error in loop body: {}", e);
-                            self.environment = prev_env;
-                            return Err(e);
-                        },
-                        _ => {}
-                    }
-                    
+                    // Define the loop variable(s), then execute the loop body
+                    let result = self.bind_pattern(pattern, item, false).and_then(|_| self.execute(body));
+
                     // Restore the environment
                     self.environment = prev_env;
+
+                    match Self::loop_flow(result?, label) {
+                        LoopFlow::Next => {}
+                        LoopFlow::Exit => break,
+                        LoopFlow::Propagate(result) => return Ok(result),
+                    }
                 }
                 println!("For loop completed");
                 Ok(RuntimeResult::Value(Value::Nil))
             }
-            Stmt::Parallel { var, range, body } => {
+            StmtKind::While { condition, body, label } => {
+                loop {
+                    let cond = self.evaluate_value(condition, "while condition")?;
+                    if !self.is_truthy(&cond) {
+                        break;
+                    }
+                    let result = self.execute(body)?;
+                    match Self::loop_flow(result, label) {
+                        LoopFlow::Next => {}
+                        LoopFlow::Exit => break,
+                        LoopFlow::Propagate(result) => return Ok(result),
+                    }
+                }
+                Ok(RuntimeResult::Value(Value::Nil))
+            }
+            StmtKind::Break(label) => Ok(RuntimeResult::Break(label.clone())),
+            StmtKind::Continue(label) => Ok(RuntimeResult::Continue(label.clone())),
+            StmtKind::Parallel { var, range, body } => {
                 println!("Executing parallel loop with variable '{}'", var);
                 
                 // Handle different range types for parallel execution
-                let (start_val, end_val) = match &range {
-                    Expr::Range { start, end } => {
-                        let s = match self.evaluate(start)? {
-                            RuntimeResult::Value(Value::Number(n)) => n,
-                            _ => 0,
-                        };
-                        let e = match self.evaluate(end)? {
-                            RuntimeResult::Value(Value::Number(n)) => n,
-                            _ => 0,
-                        };
-                        (s, e)
-                    },
-                    _ => {
-                        // Try to evaluate as a single number (0..n)
-                        match self.evaluate(&range)? {
-                            RuntimeResult::Value(Value::Number(count)) => (0, count),
-                            _ => {
-                                println!("Warning: Parallel loop range is not a valid range");
-                                return Ok(RuntimeResult::Value(Value::Nil));
-                            }
-                        }
+                let (start_val, end_val, step) = match self.loop_bounds(range)? {
+                    Some(bounds) => bounds,
+                    None => {
+                        println!("Warning: Parallel loop range is not a valid range");
+                        return Ok(RuntimeResult::Value(Value::Nil));
                     }
                 };
                 
-                println!("Using GPU runtime for parallel execution over range {}..{}", start_val, end_val);
+                println!("Using GPU runtime for parallel execution over range {}..{} step {}", start_val, end_val, step);
                 
                 // Convert the range to an expression for the GPU runtime
-                let range_expr = Expr::Range {
-                    start: Box::new(Expr::Number(start_val)),
-                    end: Box::new(Expr::Number(end_val)),
-                };
-                
-                // Execute in parallel on the GPU
-                match self.gpu_runtime.execute_parallel(&var, &range_expr, body) {
-                    Ok(_) => {
-                        println!("GPU parallel execution completed successfully");
-                        Ok(RuntimeResult::Value(Value::Nil))
+                let number = |n| Some(Box::new(Expr::new(ExprKind::Number(n), range.span)));
+                let range_expr = Expr::new(
+                    ExprKind::Range {
+                        start: number(start_val),
+                        end: number(end_val),
+                        step: number(step),
                     },
+                    range.span,
+                );
+                
+                // Launch on the (simulated) GPU, then run the body once per lane
+                match self.gpu_runtime.execute_parallel(var, &range_expr, body) {
+                    Ok(_) => println!("GPU parallel execution completed successfully"),
                     Err(e) => {
                         println!("GPU parallel execution failed: {}", e);
                         println!("Falling back to sequential execution");
-                        
-                        // Fall back to sequential execution
-                        for i in start_val..end_val {
-                            let loop_env = Environment::with_parent(Rc::clone(&self.environment));
-                            let prev_env = std::mem::replace(&mut self.environment, loop_env);
-                            
-                            self.environment.borrow_mut().define(var.clone(), Value::Number(i));
-                            
-                            let _ = self.execute(body); // Ignore returns in parallel execution
-                            
-                            self.environment = prev_env;
-                        }
-                        Ok(RuntimeResult::Value(Value::Nil))
                     }
                 }
+
+                self.parallel_depth += 1;
+                let result = self.run_lanes(var, (start_val..end_val).step_by(step as usize), body);
+                self.parallel_depth -= 1;
+                result.map(|_| RuntimeResult::Value(Value::Nil))
             }
-            Stmt::Return(expr) => {
+            StmtKind::Return(expr) => {
                 let result = self.evaluate(expr)?;
                 match result {
                     RuntimeResult::Value(v) => Ok(RuntimeResult::Return(v)),
-                    RuntimeResult::Return(_) => Err("Nested return not supported".to_string()),
-                    RuntimeResult::None => Ok(RuntimeResult::Return(Value::Nil)),
+                    _ => Err("Nested return not supported".into()),
                 }
             }
-            Stmt::FunctionDef { name, params, return_type: _, body, gpu } => {
-                // Register the function in the environment, storing its body and param names
-                let param_names: Vec<String> = params.iter()
-                    .map(|(name, _)| name.clone())
-                    .collect();
-
-                self.environment.borrow_mut().define(
-                    name.clone(),
-                    Value::Function(name.clone(), param_names.clone(), body.clone(), *gpu)
-                );
+            StmtKind::Struct { name, fields, doc } => {
+                self.record_doc(name, doc);
+                self.structs.insert(name.clone(), fields.clone());
+                Ok(RuntimeResult::Value(Value::Nil))
+            }
+            StmtKind::FunctionDef { name, params, return_type: _, body, gpu, doc } => {
+                self.record_doc(name, doc);
 
-                if *gpu {
-                    println!("GPU function '{}' registered", name);
-                    // Register with GPU runtime too
-                    let param_types: Vec<(String, String)> = params.iter()
-                        .map(|(name, type_opt)| (name.clone(), type_opt.clone().unwrap_or_else(|| "Any".to_string())))
-                        .collect();
+                // Register the function in the environment, storing its body and param names
+                let function = self.define_function(name, params, body, *gpu);
+                self.environment.borrow_mut().define(name.clone(), function);
 
-                    match self.gpu_runtime.register_function(name.clone(), param_types, body) {
-                        Ok(_) => {},
-                        Err(e) => println!("Warning: Failed to register GPU function: {}", e),
+                Ok(RuntimeResult::Value(Value::Nil))
+            }
+            StmtKind::Enum { name, variants, doc } => {
+                self.record_doc(name, doc);
+                self.enums.insert(name.clone(), variants.clone());
+                Ok(RuntimeResult::Value(Value::Nil))
+            }
+            StmtKind::Impl { type_name, methods } => {
+                for method in methods {
+                    if let StmtKind::FunctionDef { name, params, body, gpu, doc, .. } = &method.kind {
+                        // Methods are known as `Type.method` for docs and GPU registration
+                        let qualified = format!("{}.{}", type_name, name);
+                        self.record_doc(&qualified, doc);
+                        let function = self.define_function(&qualified, params, body, *gpu);
+                        self.methods.entry(type_name.clone()).or_default().insert(name.clone(), function);
                     }
                 }
-
                 Ok(RuntimeResult::Value(Value::Nil))
             }
             #[allow(unreachable_patterns)]
@@ -365,230 +768,888 @@ impl Interpreter {
         }
     }
 
-    fn evaluate(&mut self, expr: &Expr) -> Result<RuntimeResult, String> {
-        match expr {
-            Expr::Number(n) => Ok(RuntimeResult::Value(Value::Number(*n))),
-            Expr::Floating(f) => Ok(RuntimeResult::Value(Value::Floating(*f))),
-            Expr::Boolean(b) => Ok(RuntimeResult::Value(Value::Boolean(*b))),
-            Expr::String(s) => Ok(RuntimeResult::Value(Value::String(s.clone()))),
-            Expr::Ident(name) => {
-                // Special case for placeholder "error" identifier
-                if name == "error" {
-                    println!("Warning: evaluating error placeholder in expression");
-                    return Ok(RuntimeResult::Value(Value::Nil));
+    // The value a function definition binds, registering `@gpu` functions with the runtime.
+    // The function captures the current scope.
+    fn define_function(&mut self, name: &str, params: &[Param], body: &Stmt, gpu: bool) -> Value {
+        if gpu {
+            println!("GPU function '{}' registered", name);
+            // Register with GPU runtime too
+            let param_types: Vec<(String, String)> = params.iter()
+                .map(|param| (param.name.clone(), param.ty.clone().unwrap_or_else(|| "Any".to_string())))
+                .collect();
+
+            match self.gpu_runtime.register_function(name.to_string(), param_types, body) {
+                Ok(_) => {},
+                Err(e) => println!("Warning: Failed to register GPU function: {}", e),
+            }
+        }
+
+        Value::Function(Rc::new(Function {
+            name: name.to_string(),
+            params: params.to_vec(),
+            body: body.clone(),
+            gpu,
+            closure: Rc::clone(&self.environment),
+        }))
+    }
+
+    fn loop_flow(result: RuntimeResult, label: &Option<String>) -> LoopFlow {
+        match result {
+            RuntimeResult::Value(_) => LoopFlow::Next,
+            RuntimeResult::Break(target) if target.is_none() || target == *label => LoopFlow::Exit,
+            RuntimeResult::Continue(target) if target.is_none() || target == *label => LoopFlow::Next,
+            other => LoopFlow::Propagate(other),
+        }
+    }
+
+    // Runs a parallel loop body once for each lane index, each in its own scope. The lanes
+    // run in lockstep, as a GPU warp does: every lane finishes a statement before any lane
+    // starts the next one.
+    fn run_lanes(&mut self, var: &str, lanes: impl Iterator<Item = i64>, body: &Stmt) -> Result<(), RuntimeError> {
+        let mut lanes: Vec<Lane> = lanes
+            .map(|i| {
+                let environment = Environment::with_parent(Rc::clone(&self.environment));
+                environment.borrow_mut().define(var.to_string(), Value::Number(i));
+                Lane { environment, done: false }
+            })
+            .collect();
+        let all: Vec<usize> = (0..lanes.len()).collect();
+        let prev_env = Rc::clone(&self.environment);
+        let result = self.run_lockstep(&mut lanes, &all, body);
+        self.environment = prev_env;
+        result
+    }
+
+    // Runs `stmt` for the `active` lanes. A `branch` group is predicated: every lane
+    // evaluates all the conditions first, then each arm runs for the lanes that take it.
+    // An `if` is predicated the same way. Any other statement, loops included, runs to the
+    // end for one lane before the next lane starts it.
+    fn run_lockstep(&mut self, lanes: &mut [Lane], active: &[usize], stmt: &Stmt) -> Result<(), RuntimeError> {
+        match &stmt.kind {
+            StmtKind::Block(statements) => {
+                // Each lane gets its own scope for the block
+                let outer: Vec<_> = active
+                    .iter()
+                    .map(|&i| {
+                        let outer = Rc::clone(&lanes[i].environment);
+                        lanes[i].environment = Environment::with_parent(Rc::clone(&outer));
+                        outer
+                    })
+                    .collect();
+                let result = statements.iter().try_for_each(|statement| self.run_lockstep(lanes, active, statement));
+                for (&i, outer) in active.iter().zip(outer) {
+                    lanes[i].environment = outer;
                 }
-                
-                let val = self.environment.borrow().get(name);
-                val.map(|v| RuntimeResult::Value(v))
-                    .ok_or(format!("Undefined variable '{}'", name))
-            }
-            Expr::Assignment { name, value } => {
-                match self.evaluate(value) {
-                    Ok(RuntimeResult::Value(v)) => {
-                        match self.environment.borrow_mut().assign(name, v.clone()) {
-                            Ok(_) => Ok(RuntimeResult::Value(v)),
-                            Err(e) => {
-                                println!("Warning: Assignment error: {}", e);
-                                Ok(RuntimeResult::Value(Value::Nil))
-                            }
+                result
+            }
+            StmtKind::Branch { arms, fallback } => {
+                let mut taken = Vec::new();
+                for &i in active.iter().filter(|&&i| !lanes[i].done) {
+                    self.environment = Rc::clone(&lanes[i].environment);
+                    let mut arm = None;
+                    for (a, (condition, _)) in arms.iter().enumerate() {
+                        let val = self.evaluate_value(condition, "branch condition")?;
+                        if arm.is_none() && self.is_truthy(&val) {
+                            arm = Some(a);
                         }
-                    },
-                    Ok(RuntimeResult::Return(_)) => {
-                        Err("Return in assignment not allowed".into())
-                    },
-                    Ok(RuntimeResult::None) => {
-                        println!("Warning: Assignment evaluated to None");
-                        Ok(RuntimeResult::Value(Value::Nil))
-                    },
-                    Err(e) => {
-                        println!("Warning: Error evaluating assignment value: {}", e);
-                        Ok(RuntimeResult::Value(Value::Nil))
+                    }
+                    taken.push((i, arm));
+                }
+                let bodies = arms.iter().map(|(_, body)| body).enumerate().map(|(a, body)| (Some(a), body));
+                for (arm, body) in bodies.chain(fallback.iter().map(|body| (None, body.as_ref()))) {
+                    let group: Vec<usize> = taken.iter().filter(|(_, t)| *t == arm).map(|(i, _)| *i).collect();
+                    if !group.is_empty() {
+                        self.run_lockstep(lanes, &group, body)?;
                     }
                 }
+                Ok(())
             }
-            Expr::Binary { left, op, right } => {
-                let l = match self.evaluate(left) {
-                    Ok(result) => result,
-                    Err(e) => {
-                        println!("Warning: Error evaluating left side of binary expression: {}", e);
-                        return Ok(RuntimeResult::Value(Value::Nil));
+            StmtKind::IfStmt { condition, then_branch, else_branch } => {
+                let (mut taken, mut not_taken) = (Vec::new(), Vec::new());
+                for &i in active.iter().filter(|&&i| !lanes[i].done) {
+                    self.environment = Rc::clone(&lanes[i].environment);
+                    let val = self.evaluate_value(condition, "if condition")?;
+                    if self.is_truthy(&val) {
+                        taken.push(i);
+                    } else {
+                        not_taken.push(i);
                     }
-                };
-                
-                let r = match self.evaluate(right) {
-                    Ok(result) => result,
-                    Err(e) => {
-                        println!("Warning: Error evaluating right side of binary expression: {}", e);
-                        return Ok(RuntimeResult::Value(Value::Nil));
+                }
+                if !taken.is_empty() {
+                    self.run_lockstep(lanes, &taken, then_branch)?;
+                }
+                match else_branch {
+                    Some(else_branch) if !not_taken.is_empty() => self.run_lockstep(lanes, &not_taken, else_branch),
+                    _ => Ok(()),
+                }
+            }
+            _ => {
+                for &i in active {
+                    if lanes[i].done {
+                        continue;
                     }
-                };
-                
-                if let (RuntimeResult::Value(left_val), RuntimeResult::Value(right_val)) = (l, r) {
-                    match self.binary_op(op, left_val, right_val) {
-                        Ok(val) => Ok(RuntimeResult::Value(val)),
-                        Err(e) => {
-                            println!("Warning: Binary operation error: {}", e);
-                            Ok(RuntimeResult::Value(Value::Nil))
-                        }
+                    self.environment = Rc::clone(&lanes[i].environment);
+                    // `return` and `continue` only end the lane
+                    if !matches!(self.execute(stmt)?, RuntimeResult::Value(_)) {
+                        lanes[i].done = true;
                     }
-                } else {
-                    Err("Return not allowed inside binary expression".to_string())
                 }
+                Ok(())
             }
-            Expr::Unary { op, expr } => {
-                let val = self.evaluate(expr)?;
-                if let RuntimeResult::Value(v) = val {
-                    let res = self.unary_op(op, v)?;
-                    Ok(RuntimeResult::Value(res))
-                } else {
-                    Err("Return not allowed in unary expression".into())
+        }
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<RuntimeResult, RuntimeError> {
+        self.evaluate_expr(expr).map_err(|e| e.at(expr.span))
+    }
+
+    fn evaluate_expr(&mut self, expr: &Expr) -> Result<RuntimeResult, RuntimeError> {
+        match &expr.kind {
+            ExprKind::Number(n) => Ok(RuntimeResult::Value(Value::Number(*n))),
+            ExprKind::Floating(f) => Ok(RuntimeResult::Value(Value::Floating(*f))),
+            ExprKind::Int32(n) => Ok(RuntimeResult::Value(Value::Int32(*n))),
+            ExprKind::UInt32(n) => Ok(RuntimeResult::Value(Value::UInt32(*n))),
+            ExprKind::Float32(f) => Ok(RuntimeResult::Value(Value::Float32(*f))),
+            ExprKind::Boolean(b) => Ok(RuntimeResult::Value(Value::Boolean(*b))),
+            ExprKind::String(s) => Ok(RuntimeResult::Value(Value::String(s.clone()))),
+            ExprKind::InterpolatedString(segments) => {
+                let mut out = String::new();
+                for segment in segments {
+                    match segment {
+                        StringSegment::Literal(text) => out.push_str(text),
+                        StringSegment::Expr(expr) => match self.evaluate(expr)? {
+                            RuntimeResult::Value(v) => out.push_str(&v.to_string()),
+                            _ => return Err("Return not allowed in string interpolation".into()),
+                        },
+                    }
                 }
+                Ok(RuntimeResult::Value(Value::String(out)))
             }
-            Expr::Grouping(inner) => self.evaluate(inner),
-            Expr::Return(expr) => {
-                let result = self.evaluate(expr)?;
-                match result {
-                    RuntimeResult::Value(val) => Ok(RuntimeResult::Return(val)),
-                    RuntimeResult::Return(_) => Err("Nested return not supported".to_string()),
-                    RuntimeResult::None => Ok(RuntimeResult::Return(Value::Nil)),
+            ExprKind::Ident(name) => {
+                let val = self.environment.borrow().get(name);
+                match val {
+                    Some(val) => Ok(RuntimeResult::Value(val)),
+                    None if builtins::is_builtin(name) => Ok(RuntimeResult::Value(Value::Builtin(name.clone()))),
+                    None => Err(format!("Undefined variable '{}'", name).into()),
                 }
             }
-            Expr::Range { start, end } => {
-                // Evaluate both start and end of the range
-                let start_val = match self.evaluate(start)? {
-                    RuntimeResult::Value(val) => val,
-                    _ => return Ok(RuntimeResult::Value(Value::Number(0))),
-                };
-                
-                let end_val = match self.evaluate(end)? {
-                    RuntimeResult::Value(val) => val,
-                    _ => return Ok(RuntimeResult::Value(Value::Number(0))),
+            ExprKind::Assignment { target, value } => {
+                let place = self.place(target)?;
+                let v = self.evaluate_value(value, "assignment")?;
+                self.write_place(&place, v.clone())?;
+                Ok(RuntimeResult::Value(v))
+            }
+            ExprKind::CompoundAssignment { target, op, value } => {
+                // The target is resolved once, then read, combined and written back
+                // with nothing else running in between
+                let place = self.place(target)?;
+                let current = self.read_place(&place)?;
+                let rhs = self.evaluate_value(value, "assignment")?;
+                let result = self.binary_op(op, current, rhs)?;
+                self.write_place(&place, result.clone())?;
+                Ok(RuntimeResult::Value(result))
+            }
+            ExprKind::Binary { left, op: op @ (Token::AndAnd | Token::OrOr), right } => {
+                // Short-circuit: the right side is only evaluated when it decides the result
+                let l = match self.evaluate(left)? {
+                    RuntimeResult::Value(v) => self.is_truthy(&v),
+                    _ => return Err("Return not allowed inside binary expression".into()),
                 };
-                
-                // For loop ranges, we return the range as a special value
-                // The loop execution will handle the actual iteration
-                match (start_val, end_val) {
-                    (Value::Number(s), Value::Number(e)) => {
-                        // Return the end value for simple loop counting
-                        Ok(RuntimeResult::Value(Value::Number(e - s)))
+                if (*op == Token::AndAnd && !l) || (*op == Token::OrOr && l) {
+                    return Ok(RuntimeResult::Value(Value::Boolean(l)));
+                }
+                match self.evaluate(right)? {
+                    RuntimeResult::Value(v) => Ok(RuntimeResult::Value(Value::Boolean(self.is_truthy(&v)))),
+                    _ => Err("Return not allowed inside binary expression".into()),
+                }
+            }
+            ExprKind::Binary { left, op, right } => {
+                let l = self.evaluate(left)?;
+                let r = self.evaluate(right)?;
+                if let (RuntimeResult::Value(left_val), RuntimeResult::Value(right_val)) = (l, r) {
+                    let res = self.binary_op(op, left_val, right_val)?;
+                    Ok(RuntimeResult::Value(res))
+                } else {
+                    Err("Return not allowed inside binary expression".into())
+                }
+            }
+            ExprKind::Unary { op, expr } => {
+                let val = self.evaluate(expr)?;
+                if let RuntimeResult::Value(v) = val {
+                    let res = self.unary_op(op, v)?;
+                    Ok(RuntimeResult::Value(res))
+                } else {
+                    Err("Return not allowed in unary expression".into())
+                }
+            }
+            ExprKind::Grouping(inner) => self.evaluate(inner),
+            ExprKind::Range { start, end, step } => {
+                // Outside loops and slices a range evaluates to how many numbers it covers
+                let bounds = self.range_bounds(start, end, step)?;
+                match (bounds.start, bounds.end) {
+                    (Some(s), Some(e)) => {
+                        let count = ((e as i128 - s as i128).max(0) as u128).div_ceil(bounds.step as u128);
+                        let count = i64::try_from(count)
+                            .map_err(|_| format!("Range {}..{} covers more numbers than an Int can hold", s, e))?;
+                        Ok(RuntimeResult::Value(Value::Number(count)))
                     },
-                    _ => Ok(RuntimeResult::Value(Value::Number(0))),
+                    _ => Err("Only slices may leave out the bounds of a range".into()),
                 }
             },
-            Expr::FunctionCall { callee, arguments } => {
-                match &**callee {
-                    Expr::Ident(name) if name == "print" => {
-                        // Handle print function specially
-                        println!("Executing built-in print function");
-                        for arg in arguments {
-                            match self.evaluate(arg) {
-                                Ok(RuntimeResult::Value(val)) => println!("Output: {}", val),
-                                _ => println!("Output: <error>"),
-                            }
+            ExprKind::Array(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate_value(element, "array literal")?);
+                }
+                Ok(RuntimeResult::Value(Value::array(values)))
+            }
+            ExprKind::Map(entries) => {
+                let mut map = BTreeMap::new();
+                for (key, value) in entries {
+                    let key_val = self.evaluate_value(key, "map literal")?;
+                    let key_val = Key::from_value(&key_val).map_err(|e| RuntimeError::from(e).at(key.span))?;
+                    map.insert(key_val, self.evaluate_value(value, "map literal")?);
+                }
+                Ok(RuntimeResult::Value(Value::map(map)))
+            }
+            ExprKind::Set(elements) => {
+                let mut set = BTreeSet::new();
+                for element in elements {
+                    let val = self.evaluate_value(element, "set literal")?;
+                    set.insert(Key::from_value(&val).map_err(|e| RuntimeError::from(e).at(element.span))?);
+                }
+                Ok(RuntimeResult::Value(Value::set(set)))
+            }
+            ExprKind::StructLiteral { name, fields } => {
+                let declared = self
+                    .structs
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("Undefined struct '{}'", name))?;
+                if let Some((field, value)) = fields.iter().find(|(field, _)| !declared.iter().any(|(d, _)| d == field)) {
+                    return Err(RuntimeError::from(format!("{} has no field '{}'", name, field)).at(value.span));
+                }
+                // Fields are stored in declaration order, whatever order the literal uses
+                let mut values = Vec::with_capacity(declared.len());
+                for (field, field_type) in &declared {
+                    let (_, value_expr) = fields
+                        .iter()
+                        .find(|(given, _)| given == field)
+                        .ok_or_else(|| format!("Missing field '{}' in {} literal", field, name))?;
+                    let value = self.evaluate_value(value_expr, "struct literal")?;
+                    let value = self.field_value(name, field, field_type, value).map_err(|e| e.at(value_expr.span))?;
+                    values.push((field.clone(), value));
+                }
+                let instance = StructValue { name: name.clone(), fields: values };
+                Ok(RuntimeResult::Value(Value::Struct(Rc::new(RefCell::new(instance)))))
+            }
+            ExprKind::Tuple(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate_value(element, "tuple")?);
+                }
+                Ok(RuntimeResult::Value(Value::Tuple(Rc::new(values))))
+            }
+            ExprKind::Index { object, index } => {
+                let container = self.evaluate_value(object, "index expression")?;
+                if let Value::Tuple(elements) = &container {
+                    let index_val = self.evaluate_value(index, "index expression")?;
+                    let element = match numeric::as_i64(&index_val) {
+                        Some(i) if i >= 0 && (i as usize) < elements.len() => elements[i as usize].clone(),
+                        Some(i) => {
+                            return Err(RuntimeError::from(format!("Index {} out of bounds for a tuple of {}", i, elements.len())).at(index.span));
                         }
-                        Ok(RuntimeResult::Value(Value::Nil))
-                    },
-                    Expr::Ident(name) => {
-                        // Check if it's a GPU function first
-                        if name.ends_with("_gpu") || name.starts_with("gpu_") {
-                            println!("Calling GPU function: {}", name);
-                            
-                            // Evaluate all arguments
-                            let mut arg_values = Vec::new();
-                            for arg in arguments {
-                                match self.evaluate(arg)? {
-                                    RuntimeResult::Value(val) => arg_values.push(val),
-                                    _ => {
-                                        return Err(format!("Invalid argument to GPU function {}", name));
-                                    }
-                                }
-                            }
-                            
-                            // Execute on the GPU
-                            match self.gpu_runtime.execute_function(name, arg_values) {
-                                Ok(result) => {
-                                    println!("GPU function returned: {:?}", result);
-                                    Ok(RuntimeResult::Value(result))
-                                },
-                                Err(e) => {
-                                    println!("GPU function execution failed: {}", e);
-                                    Err(format!("GPU function execution error: {}", e))
-                                }
-                            }
-                        } else {
-                            // Look up user-defined function
-                            let function_result = {
-                                // Scope the borrow to avoid the borrow checker error
-                                let env_ref = self.environment.borrow();
-                                match env_ref.get(name) {
-                                    Some(value) => Some(value.clone()),
-                                    None => None
-                                }
-                            };
-                            
-                            match function_result {
-                                Some(Value::Function(fn_name, param_names, fn_body, is_gpu)) => {
-                                    println!("Calling {} function: {}", if is_gpu { "GPU" } else { "CPU" }, fn_name);
-
-                                    // Evaluate all arguments
-                                    let mut arg_values = Vec::new();
-                                    for arg in arguments {
-                                        match self.evaluate(arg)? {
-                                            RuntimeResult::Value(val) => arg_values.push(val),
-                                            _ => {
-                                                return Err(format!("Invalid argument to function {}", name));
-                                            }
-                                        }
-                                    }
-
-                                    if arg_values.len() != param_names.len() {
-                                        return Err(format!(
-                                            "Function '{}' expects {} arguments, got {}",
-                                            fn_name,
-                                            param_names.len(),
-                                            arg_values.len()
-                                        ));
-                                    }
-
-                                    // Create a new environment for the function call
-                                    let prev_env = Rc::clone(&self.environment);
-                                    let func_env = Environment::with_parent(prev_env.clone());
-                                    self.environment = func_env;
-
-                                    // Bind arguments to parameter names
-                                    for (param, value) in param_names.iter().zip(arg_values.into_iter()) {
-                                        self.environment.borrow_mut().define(param.clone(), value);
-                                    }
-
-                                    // Execute the function body
-                                    let result = match self.execute(&fn_body) {
-                                        Ok(RuntimeResult::Return(val)) => Ok(RuntimeResult::Value(val)),
-                                        Ok(RuntimeResult::Value(val)) => Ok(RuntimeResult::Value(val)),
-                                        Ok(_) => Ok(RuntimeResult::Value(Value::Nil)),
-                                        Err(e) => Err(e),
-                                    };
-
-                                    // Restore previous environment
-                                    self.environment = prev_env;
-
-                                    result
-                                },
-                                _ => {
-                                    println!("Warning: Undefined function: {}", name);
-                                    Err(format!("Undefined function: {}", name))
-                                }
+                        None => {
+                            let other = index_val;
+                            return Err(RuntimeError::from(format!("Tuple index must be Int, not {}", other.type_name())).at(index.span));
+                        }
+                    };
+                    return Ok(RuntimeResult::Value(element));
+                }
+                if let Value::Map(entries) = &container {
+                    let index_val = self.evaluate_value(index, "index expression")?;
+                    let key = Key::from_value(&index_val).map_err(|e| RuntimeError::from(e).at(index.span))?;
+                    let value = Self::map_get(&entries.borrow(), &key).map_err(|e| e.at(index.span))?;
+                    return Ok(RuntimeResult::Value(value));
+                }
+                let slice = container
+                    .as_slice()
+                    .ok_or_else(|| format!("Cannot index into {}", container.type_name()))?;
+                if let ExprKind::Range { start, end, step } = &index.kind {
+                    let bounds = self.range_bounds(start, end, step)?;
+                    let view = Self::slice(&slice, bounds).map_err(|e| RuntimeError::from(e).at(index.span))?;
+                    return Ok(RuntimeResult::Value(Value::Slice(view)));
+                }
+                let index_val = self.evaluate_value(index, "index expression")?;
+                let position = slice
+                    .checked_position(&index_val)
+                    .map_err(|e| RuntimeError::from(e).at(index.span))?;
+                let element = slice.data.borrow()[position].clone();
+                Ok(RuntimeResult::Value(element))
+            }
+            ExprKind::Field { object, name } => {
+                if let Some(enum_name) = self.enum_name(object) {
+                    // `Color.Red`: a variant without payload
+                    return Ok(RuntimeResult::Value(self.construct_variant(&enum_name, name, Vec::new())?));
+                }
+                let val = self.evaluate_value(object, "field access")?;
+                match (&val, name.as_str()) {
+                    (Value::Array(elements), "length") => Ok(RuntimeResult::Value(Value::Number(elements.borrow().len() as i64))),
+                    (Value::Slice(slice), "length") => Ok(RuntimeResult::Value(Value::Number(slice.len as i64))),
+                    (Value::String(s), "length") => Ok(RuntimeResult::Value(Value::Number(s.chars().count() as i64))),
+                    (Value::Map(entries), "length") => Ok(RuntimeResult::Value(Value::Number(entries.borrow().len() as i64))),
+                    (Value::Set(elements), "length") => Ok(RuntimeResult::Value(Value::Number(elements.borrow().len() as i64))),
+                    (Value::Tuple(elements), "length") => Ok(RuntimeResult::Value(Value::Number(elements.len() as i64))),
+                    (Value::Struct(instance), _) => {
+                        let instance = instance.borrow();
+                        match instance.fields.iter().find(|(field, _)| field == name) {
+                            Some((_, value)) => Ok(RuntimeResult::Value(value.clone())),
+                            None => Err(format!("{} has no field '{}'", instance.name, name).into()),
+                        }
+                    }
+                    _ => Err(format!("{} has no field '{}'", val.type_name(), name).into()),
+                }
+            }
+            ExprKind::FunctionCall { callee, arguments } => {
+                // Any expression can be called; a name that is not a variable may still be
+                // a built-in function
+                let function = match &callee.kind {
+                    ExprKind::Ident(name) => {
+                        let found = self.environment.borrow().get(name);
+                        match found {
+                            Some(value) => value,
+                            None if builtins::is_builtin(name) => Value::Builtin(name.clone()),
+                            None => {
+                                println!("Warning: Undefined function: {}", name);
+                                return Err(format!("Undefined function: {}", name).into());
                             }
                         }
-                    },
-                    _ => {
-                        println!("Warning: Callee is not a function name");
-                        Err("Invalid function call: callee is not a function name".to_string())
                     }
+                    _ => self.evaluate_value(callee, "callee")?,
+                };
+
+                // Evaluate all arguments
+                let mut arg_values = Vec::with_capacity(arguments.len());
+                for arg in arguments {
+                    arg_values.push(self.evaluate_value(arg, "function argument")?);
+                }
+
+                self.call_value(function, arg_values)
+            }
+            ExprKind::Lambda { params, body } => {
+                let function = Function {
+                    name: "<anonymous>".to_string(),
+                    params: params.clone(),
+                    body: (**body).clone(),
+                    gpu: false,
+                    closure: Rc::clone(&self.environment),
+                };
+                Ok(RuntimeResult::Value(Value::Function(Rc::new(function))))
+            }
+            ExprKind::Match { subject, arms } => {
+                let value = self.evaluate_value(subject, "match subject")?;
+                for arm in arms {
+                    let mut bindings = Vec::new();
+                    if !self.match_pattern(&arm.pattern, &value, &mut bindings)? {
+                        continue;
+                    }
+                    // Bindings are visible in the guard and the body only
+                    let previous = Rc::clone(&self.environment);
+                    self.environment = Environment::with_parent(previous.clone());
+                    for (name, bound) in bindings {
+                        self.environment.borrow_mut().define(name, bound);
+                    }
+                    let result = self.run_arm(arm);
+                    self.environment = previous;
+                    if let Some(result) = result? {
+                        return Ok(result);
+                    }
+                }
+                Err(format!("No match arm matches {}", value).into())
+            }
+            ExprKind::MethodCall { receiver, method, arguments } => {
+                if let Some(enum_name) = self.enum_name(receiver) {
+                    // `Shape.Circle(2.0)` constructs a variant
+                    let mut payload = Vec::with_capacity(arguments.len());
+                    for arg in arguments {
+                        payload.push(self.evaluate_value(arg, "variant payload")?);
+                    }
+                    return Ok(RuntimeResult::Value(self.construct_variant(&enum_name, method, payload)?));
+                }
+                let receiver_val = self.evaluate_value(receiver, "method receiver")?;
+                let mut arg_values = Vec::with_capacity(arguments.len() + 1);
+                for arg in arguments {
+                    arg_values.push(self.evaluate_value(arg, "method argument")?);
+                }
+
+                // Methods from `impl` blocks come first, then the built-in ones
+                let type_name = Self::method_type(&receiver_val);
+                let user_method = self.methods.get(&type_name).and_then(|methods| methods.get(method)).cloned();
+                if let Some(Value::Function(function)) = user_method {
+                    arg_values.insert(0, receiver_val);
+                    return self.call_function(&function, arg_values);
+                }
+                // A struct field holding a function is called like a method, without `self`
+                if let Value::Struct(instance) = &receiver_val {
+                    let field = instance.borrow().fields.iter().find(|(name, _)| name == method).map(|(_, v)| v.clone());
+                    if let Some(function @ (Value::Function(_) | Value::Builtin(_))) = field {
+                        return self.call_value(function, arg_values);
+                    }
+                }
+                let value = builtins::call_method(&receiver_val, method, arg_values)?;
+                Ok(RuntimeResult::Value(value))
+            }
+        }
+    }
+
+    // The enum an expression such as `Shape` in `Shape.Circle(r)` names, unless a variable
+    // of that name hides it
+    fn enum_name(&self, expr: &Expr) -> Option<String> {
+        match &expr.kind {
+            ExprKind::Ident(name) if self.enums.contains_key(name) && self.environment.borrow().get(name).is_none() => {
+                Some(name.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn construct_variant(&self, enum_name: &str, variant: &str, payload: Vec<Value>) -> Result<Value, RuntimeError> {
+        let types = self
+            .enums
+            .get(enum_name)
+            .and_then(|variants| variants.iter().find(|(name, _)| name == variant))
+            .map(|(_, types)| types.clone())
+            .ok_or_else(|| format!("{} has no variant '{}'", enum_name, variant))?;
+        if types.len() != payload.len() {
+            return Err(format!("{}.{} takes {} value(s), got {}", enum_name, variant, types.len(), payload.len()).into());
+        }
+        let owner = format!("{}.{}", enum_name, variant);
+        let payload = types
+            .iter()
+            .zip(payload)
+            .enumerate()
+            .map(|(i, (declared, value))| self.field_value(&owner, &i.to_string(), declared, value))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::Variant(Rc::new(VariantValue {
+            enum_name: enum_name.to_string(),
+            variant: variant.to_string(),
+            payload,
+        })))
+    }
+
+    // Whether some enum declares a variant of this name, which makes a bare name in a
+    // pattern a variant pattern rather than a binding
+    fn is_variant_name(&self, name: &str) -> bool {
+        self.enums.values().any(|variants| variants.iter().any(|(variant, _)| variant == name))
+    }
+
+    // Binds the names in a `let` or `for` pattern in the current scope. Unlike in a
+    // `match`, a value the pattern does not fit is an error.
+    fn bind_pattern(&mut self, pattern: &Pattern, value: Value, mutable: bool) -> Result<(), RuntimeError> {
+        let mut bindings = Vec::new();
+        if !self.match_pattern(pattern, &value, &mut bindings)? {
+            return Err(RuntimeError::from(format!("Pattern `{}` does not match {}", pattern, value)).at(pattern.span));
+        }
+        let mut environment = self.environment.borrow_mut();
+        for (name, bound) in bindings {
+            if mutable {
+                environment.define_mutable(name, bound);
+            } else {
+                environment.define(name, bound);
+            }
+        }
+        Ok(())
+    }
+
+    // Tests `value` against `pattern`, collecting the names it binds
+    fn match_pattern(&mut self, pattern: &Pattern, value: &Value, bindings: &mut Vec<(String, Value)>) -> Result<bool, RuntimeError> {
+        match &pattern.kind {
+            PatternKind::Wildcard => Ok(true),
+            PatternKind::Binding(name) if self.is_variant_name(name) => Ok(matches!(
+                value,
+                Value::Variant(v) if v.variant == *name && v.payload.is_empty()
+            )),
+            PatternKind::Binding(name) => {
+                bindings.push((name.clone(), value.clone()));
+                Ok(true)
+            }
+            PatternKind::Literal(literal) => {
+                let literal = self.evaluate_value(literal, "pattern")?;
+                Ok(Self::values_equal(&literal, value))
+            }
+            PatternKind::Range { start, end } => {
+                let start = self.evaluate_value(start, "pattern")?;
+                let end = self.evaluate_value(end, "pattern")?;
+                let above = numeric::compare(value, &start).is_some_and(Ordering::is_ge);
+                let below = numeric::compare(value, &end).is_some_and(Ordering::is_lt);
+                Ok(above && below)
+            }
+            PatternKind::Variant { enum_name, name, fields } => {
+                let Value::Variant(variant) = value else {
+                    return Ok(false);
+                };
+                if variant.variant != *name || enum_name.as_ref().is_some_and(|e| *e != variant.enum_name) {
+                    return Ok(false);
+                }
+                if fields.len() != variant.payload.len() {
+                    return Err(RuntimeError::from(format!(
+                        "{}.{} holds {} value(s), the pattern lists {}",
+                        variant.enum_name,
+                        name,
+                        variant.payload.len(),
+                        fields.len()
+                    ))
+                    .at(pattern.span));
+                }
+                self.match_all(fields, &variant.payload, bindings)
+            }
+            PatternKind::Tuple(patterns) => match value {
+                Value::Tuple(elements) if elements.len() == patterns.len() => self.match_all(patterns, elements, bindings),
+                _ => Ok(false),
+            },
+            PatternKind::Array(patterns) => match value.as_slice().map(|slice| slice.elements()) {
+                Some(elements) if elements.len() == patterns.len() => self.match_all(patterns, &elements, bindings),
+                _ => Ok(false),
+            },
+            PatternKind::Struct { name, fields } => {
+                let Value::Struct(instance) = value else {
+                    return Ok(false);
+                };
+                if instance.borrow().name != *name {
+                    return Ok(false);
+                }
+                for (field, field_pattern) in fields {
+                    let field_value = instance.borrow().fields.iter().find(|(f, _)| f == field).map(|(_, v)| v.clone());
+                    let Some(field_value) = field_value else {
+                        return Err(RuntimeError::from(format!("{} has no field '{}'", name, field)).at(field_pattern.span));
+                    };
+                    if !self.match_pattern(field_pattern, &field_value, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    fn match_all(&mut self, patterns: &[Pattern], values: &[Value], bindings: &mut Vec<(String, Value)>) -> Result<bool, RuntimeError> {
+        for (pattern, value) in patterns.iter().zip(values) {
+            if !self.match_pattern(pattern, value, bindings)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // The guard, then the body of an arm whose pattern matched; None if the guard fails
+    fn run_arm(&mut self, arm: &MatchArm) -> Result<Option<RuntimeResult>, RuntimeError> {
+        if let Some(guard) = &arm.guard {
+            let passed = self.evaluate_value(guard, "match guard")?;
+            if !self.is_truthy(&passed) {
+                return Ok(None);
+            }
+        }
+        self.execute(&arm.body).map(Some)
+    }
+
+    fn call_value(&mut self, function: Value, arg_values: Vec<Value>) -> Result<RuntimeResult, RuntimeError> {
+        match function {
+            Value::Function(function) => self.call_function(&function, arg_values),
+            Value::Builtin(name) if name == "print" => {
+                if let Some(output) = &mut self.output {
+                    let line: Vec<String> = arg_values.iter().map(Value::to_string).collect();
+                    output.push_str(&line.join(" "));
+                    output.push('\n');
+                    return Ok(RuntimeResult::Value(Value::Nil));
+                }
+                // Handle print function specially
+                println!("Executing built-in print function");
+                for val in arg_values {
+                    println!("Output: {}", val);
+                }
+                Ok(RuntimeResult::Value(Value::Nil))
+            }
+            Value::Builtin(name) => {
+                let value = builtins::call(&name, arg_values).map_err(|e| format!("{}: {}", name, e))?;
+                Ok(RuntimeResult::Value(value))
+            }
+            other => Err(format!("{} is not a function", other.type_name()).into()),
+        }
+    }
+
+    // Runs a user-defined function or method on already evaluated arguments
+    fn call_function(&mut self, function: &Function, mut arg_values: Vec<Value>) -> Result<RuntimeResult, RuntimeError> {
+        let Function { name: fn_name, params: param_names, body: fn_body, gpu: is_gpu, closure } = function;
+        println!("Calling {} function: {}", if *is_gpu { "GPU" } else { "CPU" }, fn_name);
+
+        if arg_values.len() != param_names.len() {
+            return Err(format!(
+                "Function '{}' expects {} arguments, got {}",
+                fn_name,
+                param_names.len(),
+                arg_values.len()
+            ).into());
+        }
+
+        // Arguments take the representation their parameter annotations ask for
+        for (param, value) in param_names.iter().zip(arg_values.iter_mut()) {
+            *value = Self::annotated(std::mem::replace(value, Value::Nil), &param.ty)
+                .map_err(|e| format!("Argument '{}' of '{}': {}", param.name, fn_name, e))?;
+        }
+
+        // The GPU runtime only simulates the launch; the kernel body itself runs below, on
+        // the same values (arrays and slices are passed by reference)
+        if *is_gpu {
+            self.gpu_runtime
+                .launch_function(fn_name, &arg_values)
+                .map_err(|e| format!("GPU function execution error: {}", e))?;
+        }
+
+        // Create a new environment for the call, inside the one the function was defined in
+        let prev_env = Rc::clone(&self.environment);
+        let func_env = Environment::with_parent(Rc::clone(closure));
+        self.environment = func_env;
+
+        // Bind arguments to parameter names
+        for (param, value) in param_names.iter().zip(arg_values) {
+            self.environment.borrow_mut().define(param.name.clone(), value);
+        }
+
+        // Execute the function body
+        let result = match self.execute(fn_body) {
+            Ok(RuntimeResult::Return(val)) => Ok(RuntimeResult::Value(val)),
+            Ok(RuntimeResult::Value(val)) => Ok(RuntimeResult::Value(val)),
+            Ok(_) => Err("`break` or `continue` outside of a loop".into()),
+            Err(e) => Err(e),
+        };
+
+        // Restore previous environment
+        self.environment = prev_env;
+
+        result
+    }
+
+    // The type name `impl` blocks are looked up under. Slices share the methods of arrays.
+    fn method_type(value: &Value) -> String {
+        match value {
+            Value::Struct(instance) => instance.borrow().name.clone(),
+            Value::Slice(_) => "Array".to_string(),
+            Value::Variant(value) => value.enum_name.clone(),
+            other => other.type_name().to_string(),
+        }
+    }
+
+    // A value stored under a `: Type` annotation. Numbers take the numeric type named
+    // (`let x: Int32 = 5`); other annotations are not enforced at run time.
+    fn annotated(value: Value, type_name: &Option<String>) -> Result<Value, String> {
+        match type_name.as_deref().and_then(NumericType::from_name) {
+            Some(numeric_type) => numeric::coerce(&value, numeric_type),
+            None => Ok(value),
+        }
+    }
+
+    fn evaluate_value(&mut self, expr: &Expr, context: &str) -> Result<Value, RuntimeError> {
+        match self.evaluate(expr)? {
+            RuntimeResult::Value(v) => Ok(v),
+            _ => Err(format!("Return in {} not allowed", context).into()),
+        }
+    }
+
+    // Resolves an assignment target to the storage it names
+    fn place(&mut self, target: &Expr) -> Result<Place, RuntimeError> {
+        match &target.kind {
+            ExprKind::Ident(name) => Ok(Place::Variable(name.clone())),
+            ExprKind::Index { object, index } => {
+                if let ExprKind::Range { .. } = index.kind {
+                    return Err(RuntimeError::from("Cannot assign to a slice; assign to its elements instead").at(index.span));
+                }
+                let container = self.evaluate_value(object, "index expression")?;
+                let index_val = self.evaluate_value(index, "index expression")?;
+                if let Value::Map(entries) = container {
+                    let key = Key::from_value(&index_val).map_err(|e| RuntimeError::from(e).at(index.span))?;
+                    return Ok(Place::MapEntry(entries, key));
+                }
+                match container.as_slice() {
+                    Some(slice) => {
+                        let position = slice
+                            .checked_position(&index_val)
+                            .map_err(|e| RuntimeError::from(e).at(index.span))?;
+                        Ok(Place::Index(slice.data, position, index.span))
+                    }
+                    None => Err(RuntimeError::from(format!("Cannot assign into an element of {}", container.type_name())).at(object.span)),
+                }
+            }
+            ExprKind::Field { object, name } => match self.evaluate_value(object, "field access")? {
+                Value::Struct(instance) => {
+                    let position = instance.borrow().fields.iter().position(|(field, _)| field == name);
+                    match position {
+                        Some(i) => Ok(Place::Field(instance, i)),
+                        None => Err(RuntimeError::from(format!("{} has no field '{}'", instance.borrow().name, name)).at(target.span)),
+                    }
+                }
+                other => Err(RuntimeError::from(format!("Cannot assign to field '{}' of {}", name, other.type_name())).at(target.span)),
+            },
+            _ => Err(RuntimeError::from("Invalid assignment target").at(target.span)),
+        }
+    }
+
+    fn read_place(&self, place: &Place) -> Result<Value, RuntimeError> {
+        match place {
+            Place::Variable(name) => self
+                .environment
+                .borrow()
+                .get(name)
+                .ok_or_else(|| format!("Undefined variable '{}'", name).into()),
+            Place::Index(elements, i, span) => match elements.borrow().get(*i) {
+                Some(element) => Ok(element.clone()),
+                None => Err(Self::shrunk(*span)),
+            },
+            Place::MapEntry(entries, key) => Self::map_get(&entries.borrow(), key),
+            Place::Field(instance, i) => Ok(instance.borrow().fields[*i].1.clone()),
+        }
+    }
+
+    fn write_place(&mut self, place: &Place, value: Value) -> Result<(), RuntimeError> {
+        match place {
+            Place::Variable(name) => Ok(self.environment.borrow_mut().assign(name, value)?),
+            Place::Index(elements, i, span) => match elements.borrow_mut().get_mut(*i) {
+                Some(element) => {
+                    *element = value;
+                    Ok(())
+                }
+                None => Err(Self::shrunk(*span)),
+            },
+            Place::MapEntry(entries, key) => {
+                entries.borrow_mut().insert(key.clone(), value);
+                Ok(())
+            }
+            Place::Field(instance, i) => {
+                let (struct_name, field) = {
+                    let instance = instance.borrow();
+                    (instance.name.clone(), instance.fields[*i].0.clone())
+                };
+                let value = match self.structs.get(&struct_name) {
+                    Some(fields) => self.field_value(&struct_name, &field, &fields[*i].1, value)?,
+                    None => value,
+                };
+                instance.borrow_mut().fields[*i].1 = value;
+                Ok(())
+            }
+        }
+    }
+
+    // An index place that fell out of bounds while the assigned value was evaluated
+    fn shrunk(span: Span) -> RuntimeError {
+        RuntimeError::from("Index out of bounds: the array was shortened during the assignment").at(span)
+    }
+
+    // The value stored in a field: it must match the type named in the struct declaration,
+    // and numbers take the numeric type named there. References (`&Matrix`, `&mut Matrix`)
+    // hold values of the type they refer to.
+    fn field_value(&self, struct_name: &str, field: &str, declared: &str, value: Value) -> Result<Value, RuntimeError> {
+        let expected = declared.trim_start_matches('&').trim_start_matches("mut ");
+        if let Some(numeric_type) = NumericType::from_name(expected) {
+            return numeric::coerce(&value, numeric_type)
+                .map_err(|e| format!("Field '{}' of {}: {}", field, struct_name, e).into());
+        }
+        let matches = match (expected, &value) {
+            ("Any", _) => true,
+            ("Array", Value::Array(_) | Value::Slice(_)) => true,
+            ("Bool", Value::Boolean(_)) => true,
+            (_, Value::Struct(instance)) => instance.borrow().name == expected,
+            (_, Value::Variant(variant)) => variant.enum_name == expected,
+            (expected, value) if value.type_name() == expected => true,
+            (expected, _)
+                if !Self::is_known_type(expected)
+                    && !self.structs.contains_key(expected)
+                    && !self.enums.contains_key(expected) =>
+            {
+                return Err(format!("Unknown type '{}' for field '{}' of {}", expected, field, struct_name).into());
+            }
+            _ => false,
+        };
+        if matches {
+            Ok(value)
+        } else {
+            let found = match value {
+                Value::Struct(instance) => instance.borrow().name.clone(),
+                Value::Variant(variant) => variant.enum_name.clone(),
+                other => other.type_name().to_string(),
+            };
+            Err(format!("Field '{}' of {} is {}, not {}", field, struct_name, declared, found).into())
+        }
+    }
+
+    fn is_known_type(name: &str) -> bool {
+        matches!(name, "Int" | "Float" | "Int32" | "UInt32" | "Float32" | "Int64" | "Float64" | "String" | "Boolean" | "Bool" | "Nil" | "Function" | "Array" | "Slice" | "Map" | "Set")
+    }
+
+    fn map_get(entries: &BTreeMap<Key, Value>, key: &Key) -> Result<Value, RuntimeError> {
+        entries.get(key).cloned().ok_or_else(|| format!("Key {} not found in map", key).into())
+    }
+
+    // Evaluates the bounds of a range expression; bounds left out stay `None`
+    fn range_bounds(
+        &mut self,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+        step: &Option<Box<Expr>>,
+    ) -> Result<RangeBounds, RuntimeError> {
+        let mut bound = |expr: &Option<Box<Expr>>| -> Result<Option<i64>, RuntimeError> {
+            match expr {
+                None => Ok(None),
+                Some(expr) => match self.evaluate_value(expr, "range")? {
+                    value if numeric::as_i64(&value).is_some() => Ok(numeric::as_i64(&value)),
+                    other => Err(RuntimeError::from(format!("Range bounds must be Int, not {}", other.type_name())).at(expr.span)),
+                },
+            }
+        };
+        let start = bound(start)?;
+        let end = bound(end)?;
+        let step = match bound(step)? {
+            None => 1,
+            Some(n) if n > 0 => n,
+            Some(n) => return Err(format!("Range step must be positive, got {}", n).into()),
+        };
+        Ok(RangeBounds { start, end, step })
+    }
+
+    // The integers a loop over `range` visits. The start defaults to 0; the end is required.
+    fn loop_bounds(&mut self, range: &Expr) -> Result<Option<(i64, i64, i64)>, RuntimeError> {
+        match &range.kind {
+            ExprKind::Range { start, end, step } => {
+                let bounds = self.range_bounds(start, end, step)?;
+                match bounds.end {
+                    Some(end) => Ok(Some((bounds.start.unwrap_or(0), end, bounds.step))),
+                    None => Err("A loop range needs an end".into()),
                 }
             }
+            _ => match self.evaluate(range)? {
+                // A single number n loops over 0..n
+                RuntimeResult::Value(count) => Ok(numeric::as_i64(&count).map(|count| (0, count, 1))),
+                _ => Ok(None),
+            },
         }
     }
 
+    // `slice[start..end step n]`: a view of the selected elements, or an error if the
+    // bounds fall outside it
+    fn slice(slice: &ArraySlice, bounds: RangeBounds) -> Result<ArraySlice, String> {
+        let len = slice.len as i64;
+        let start = bounds.start.unwrap_or(0);
+        let end = bounds.end.unwrap_or(len);
+        if start < 0 || end > len || start > end {
+            return Err(format!("Slice {}..{} out of bounds for length {}", start, end, len));
+        }
+        let count = (end - start) as usize;
+        Ok(ArraySlice {
+            data: Rc::clone(&slice.data),
+            start: slice.start + start as usize * slice.step,
+            len: count.div_ceil(bounds.step as usize),
+            step: slice.step * bounds.step as usize,
+        })
+    }
+
     fn is_truthy(&self, val: &Value) -> bool {
         match val {
             Value::Boolean(b) => *b,
@@ -601,70 +1662,420 @@ impl Interpreter {
         use Value::*;
         match op {
             Token::Plus => match (left, right) {
-                (Number(a), Number(b)) => Ok(Number(a + b)),
-                (Floating(a), Floating(b)) => Ok(Floating(a + b)),
                 (String(a), String(b)) => Ok(String(a + &b)),
-                _ => Err("Invalid '+' operands".to_string()),
+                (left, right) => numeric::arithmetic(op, &left, &right),
             },
-            Token::Minus => self.numeric_op(left, right, |a, b| a - b),
-            Token::Star => self.numeric_op(left, right, |a, b| a * b),
-            Token::Slash => self.numeric_op(left, right, |a, b| a / b),
-            Token::EQ => Ok(Boolean(left == right)),
-            Token::NE => Ok(Boolean(left != right)),
-            Token::GT => self.compare_op(left, right, |a, b| a > b),
-            Token::LT => self.compare_op(left, right, |a, b| a < b),
-            Token::GE => self.compare_op(left, right, |a, b| a >= b),
-            Token::LE => self.compare_op(left, right, |a, b| a <= b),
+            Token::Minus | Token::Star | Token::Slash | Token::Percent => numeric::arithmetic(op, &left, &right),
+            Token::StarStar => numeric::power(&left, &right),
+            Token::Amp | Token::Pipe | Token::Caret => self.bitwise_op(op, left, right),
+            Token::Shl | Token::Shr => numeric::bitwise(op, &left, &right),
+            Token::EQ => Ok(Boolean(Self::values_equal(&left, &right))),
+            Token::NE => Ok(Boolean(!Self::values_equal(&left, &right))),
+            Token::GT => self.compare_op(left, right, Ordering::is_gt),
+            Token::LT => self.compare_op(left, right, Ordering::is_lt),
+            Token::GE => self.compare_op(left, right, Ordering::is_ge),
+            Token::LE => self.compare_op(left, right, Ordering::is_le),
             _ => Err("Unknown binary op".into()),
         }
     }
 
+    // `==`: arrays and slices compare by their elements, so `a[0..2] == [1, 2]`. Numbers
+    // compare by value whatever their types, so `1i32 == 1`.
+    pub(crate) fn values_equal(left: &Value, right: &Value) -> bool {
+        match (left.as_slice(), right.as_slice()) {
+            (Some(l), Some(r)) => {
+                let (l, r) = (l.elements(), r.elements());
+                l.len() == r.len() && l.iter().zip(&r).all(|(a, b)| Self::values_equal(a, b))
+            }
+            _ if NumericType::of(left).is_some() && NumericType::of(right).is_some() => {
+                numeric::compare(left, right) == Some(Ordering::Equal)
+            }
+            _ => left == right,
+        }
+    }
+
     fn unary_op(&self, op: &Token, val: Value) -> Result<Value, String> {
         match op {
-            Token::Minus => match val {
-                Value::Number(n) => Ok(Value::Number(-n)),
-                Value::Floating(f) => Ok(Value::Floating(-f)),
-                _ => Err("Invalid unary minus".into()),
-            },
+            Token::Minus => numeric::negate(&val),
+            Token::Bang => Ok(Value::Boolean(!self.is_truthy(&val))),
             _ => Err("Unknown unary operator".to_string()),
         }
     }
 
-    fn numeric_op<F>(&self, left: Value, right: Value, f: F) -> Result<Value, String>
-    where
-        F: Fn(i64, i64) -> i64,
-    {
+    fn bitwise_op(&self, op: &Token, left: Value, right: Value) -> Result<Value, String> {
+        use Value::*;
         match (left, right) {
-            (Value::Number(a), Value::Number(b)) => Ok(Value::Number(f(a, b))),
-            (Value::Floating(a), Value::Floating(b)) => {
-                // Convert the operation to work on floating point
-                let float_result = f(a as i64, b as i64) as f64;
-                Ok(Value::Floating(float_result))
-            },
-            (Value::Number(a), Value::Floating(b)) => {
-                // Mixed number types
-                let float_result = f(a, b as i64) as f64;
-                Ok(Value::Floating(float_result))
-            },
-            (Value::Floating(a), Value::Number(b)) => {
-                // Mixed number types
-                let float_result = f(a as i64, b) as f64;
-                Ok(Value::Floating(float_result))
-            },
-            _ => {
-                println!("Warning: Expected numeric operands");
-                Ok(Value::Number(0)) // Default to 0 for error recovery
-            },
+            (Boolean(a), Boolean(b)) => Ok(Boolean(match op {
+                Token::Amp => a & b,
+                Token::Pipe => a | b,
+                _ => a ^ b,
+            })),
+            (left, right) => numeric::bitwise(op, &left, &right),
         }
     }
 
     fn compare_op<F>(&self, left: Value, right: Value, f: F) -> Result<Value, String>
     where
-        F: Fn(i64, i64) -> bool,
+        F: Fn(Ordering) -> bool,
     {
-        match (left, right) {
-            (Value::Number(a), Value::Number(b)) => Ok(Value::Boolean(f(a, b))),
-            _ => Err("Expected integer operands".to_string()),
+        if NumericType::of(&left).is_none() || NumericType::of(&right).is_none() {
+            return Err(format!("Cannot compare {} with {}", left.type_name(), right.type_name()));
         }
+        let ordering = numeric::compare(&left, &right);
+        // Every comparison with NaN is false
+        Ok(Value::Boolean(ordering.is_some_and(f)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    // What the program prints, or the message of the runtime error that stopped it
+    fn run(source: &str) -> Result<String, String> {
+        let tokens = Lexer::new(source).tokenize().expect("source should lex");
+        let program = Parser::new(tokens).parse().expect("source should parse");
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.interpret(program).map_err(|e| e.message)?;
+        Ok(interpreter.take_output())
+    }
+
+    #[test]
+    fn interpolations_are_evaluated_in_place() {
+        let source = "let name = \"Ada\"\nlet n = 2\nprint(\"${name} has ${n * 3} items\\n\\tok\")\n";
+        assert_eq!(run(source), Ok("Ada has 6 items\n\tok\n".to_string()));
+    }
+
+    #[test]
+    fn operators_evaluate() {
+        let source = "print(7 % 3, -7 % 3, 2 ** 10, 6 & 3, 6 | 3, 6 ^ 3, 1 << 4, -16 >> 2, !true)\n";
+        assert_eq!(run(source), Ok("1 -1 1024 2 7 5 16 -4 false\n".to_string()));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let source = "fn boom():\n    return 1 / 0\nprint(false && boom() == 1, true || boom() == 1)\n";
+        assert_eq!(run(source), Ok("false true\n".to_string()));
+    }
+
+    #[test]
+    fn assigning_past_an_array_shortened_by_the_value_is_an_error() {
+        let source = "let a = [1, 2, 3]\nfn shrink():\n    a.pop()\n    a.pop()\n    return 9\na[2] = shrink()\n";
+        assert_eq!(run(source), Err("Index out of bounds: the array was shortened during the assignment".to_string()));
+    }
+
+    #[test]
+    fn compound_assignment_updates_variables_and_elements() {
+        let source = "let mut x = 10\nx += 5\nx -= 3\nx *= 2\nx /= 4\nlet a = [1, 2]\na[1] *= 10\nprint(x, a)\n";
+        assert_eq!(run(source), Ok("6 [1, 20]\n".to_string()));
+    }
+
+    #[test]
+    fn compound_assignment_past_an_array_shortened_by_the_value_is_an_error() {
+        let source = "let a = [1, 2, 3]\nfn shrink():\n    a.pop()\n    a.pop()\n    return 9\na[2] += shrink()\n";
+        assert_eq!(run(source), Err("Index out of bounds: the array was shortened during the assignment".to_string()));
+    }
+
+    #[test]
+    fn the_first_true_clause_of_a_chain_runs() {
+        let source = "\
+fn classify(n):
+    if n < 0:
+        return \"negative\"
+    then n == 0:
+        return \"zero\"
+    then n < 10:
+        if n % 2 == 0:
+            return \"small even\"
+        else:
+            return \"small odd\"
+    then n < 100:
+        return \"medium\"
+    else:
+        return \"large\"
+for n in [-5, 0, 4, 7, 50, 500]:
+    print(classify(n))
+";
+        assert_eq!(run(source), Ok("negative\nzero\nsmall even\nsmall odd\nmedium\nlarge\n".to_string()));
+    }
+
+    #[test]
+    fn only_the_first_matching_arm_runs() {
+        let source = "let x = 5\nbranch x > 10 => print(\"big\")\nbranch x == 5 => print(\"five\")\nfallback => print(\"other\")\nbranch x > 10 => print(\"big\")\nfallback =>\n    print(\"other\")\n    print(\"done\")\n";
+        assert_eq!(run(source), Ok("five\nother\ndone\n".to_string()));
+    }
+
+    #[test]
+    fn branch_groups_are_predicated_across_lanes() {
+        let source = "let a = [0, 0, 0, 0]\nparallel i in 0..4:\n    branch a[0] == 0 => a[i] = 1\n    fallback => a[i] = 2\nprint(a)\n";
+        assert_eq!(run(source), Ok("[1, 1, 1, 1]\n".to_string()));
+    }
+
+    #[test]
+    fn ifs_in_a_parallel_body_are_predicated_across_lanes() {
+        let source = "\
+parallel i in 0..4:
+    if i < 2:
+        branch i == 0 => print(\"zero\", i)
+        fallback => print(\"one\", i)
+        print(\"low\", i)
+    else:
+        print(\"high\", i)
+";
+        assert_eq!(run(source), Ok("zero 0\none 1\nlow 0\nlow 1\nhigh 2\nhigh 3\n".to_string()));
+    }
+
+    // Loops are not run in lockstep: each lane runs the whole loop before the next starts it
+    #[test]
+    fn loops_in_a_parallel_body_run_one_lane_at_a_time() {
+        let source = "parallel i in 0..2:\n    for j in 0..2:\n        branch j == 0 => print(i, \"first\")\n        fallback => print(i, \"then\")\n";
+        assert_eq!(run(source), Ok("0 first\n0 then\n1 first\n1 then\n".to_string()));
+    }
+
+    #[test]
+    fn while_loops_break_and_continue() {
+        let source = "\
+let mut n = 0
+let mut odd = []
+while true:
+    n += 1
+    if n > 7:
+        break
+    if n % 2 == 0:
+        continue
+    odd.push(n)
+print(odd)
+";
+        assert_eq!(run(source), Ok("[1, 3, 5, 7]\n".to_string()));
+    }
+
+    #[test]
+    fn labels_pick_the_loop_to_leave() {
+        let source = "\
+outer: for i in 0..3:
+    let mut j = 0
+    while j < 3:
+        j += 1
+        if j == 2:
+            continue outer
+        if i == 2:
+            break outer
+        print(i, j)
+print(\"done\")
+";
+        assert_eq!(run(source), Ok("0 1\n1 1\ndone\n".to_string()));
+    }
+
+    #[test]
+    fn arrays_are_indexed_and_updated_in_place() {
+        let source = "let arr = [1.0, 2.0, 3.0]\nparallel i in 0..arr.length:\n    arr[i] = arr[i] * arr[i]\nprint(arr, arr.length)\n";
+        assert_eq!(run(source), Ok("[1.0, 4.0, 9.0] 3\n".to_string()));
+    }
+
+    #[test]
+    fn arrays_are_shared_by_reference() {
+        let source = "let a = [1, 2]\nlet b = a\nb[0] = 7\nfn set(xs):\n    xs[1] = 8\nset(a)\nprint(a)\n";
+        assert_eq!(run(source), Ok("[7, 8]\n".to_string()));
+    }
+
+    #[test]
+    fn indexes_are_bounds_checked() {
+        assert_eq!(run("print([1, 2, 3][3])\n"), Err("Index 3 out of bounds for length 3".to_string()));
+        assert_eq!(run("let a = [1]\na[-1] = 2\n"), Err("Index -1 out of bounds for length 1".to_string()));
+        assert_eq!(run("print([1][true])\n"), Err("Index must be an Int, not Boolean".to_string()));
+    }
+
+    #[test]
+    fn slices_share_the_array_they_view() {
+        let source = "let a = [1, 2, 3, 4, 5]\nlet s = a[1..4]\ns[0] = 20\na[3] = 40\nprint(a, s)\n";
+        assert_eq!(run(source), Ok("[1, 20, 3, 40, 5] [20, 3, 40]\n".to_string()));
+    }
+
+    #[test]
+    fn slices_of_slices_compose() {
+        let source = "let a = [0, 1, 2, 3, 4, 5, 6, 7]\nlet evens = a[0..8 step 2]\nlet inner = evens[1..3]\ninner[1] = 99\nprint(a, inner.length)\n";
+        assert_eq!(run(source), Ok("[0, 1, 2, 3, 99, 5, 6, 7] 2\n".to_string()));
+    }
+
+    #[test]
+    fn parallel_loops_update_a_slice_in_place() {
+        let source = "let a = [1, 2, 3, 4, 5, 6]\nlet tail = a[3..]\nparallel i in 0..tail.length:\n    tail[i] = 0\nprint(a)\n";
+        assert_eq!(run(source), Ok("[1, 2, 3, 0, 0, 0]\n".to_string()));
+    }
+
+    #[test]
+    fn indexing_past_a_slice_is_an_error() {
+        let source = "let a = [1, 2, 3]\nlet s = a[0..2]\nprint(s[2])\n";
+        assert_eq!(run(source), Err("Index 2 out of bounds for length 2".to_string()));
+    }
+
+    #[test]
+    fn ranges_count_without_overflowing() {
+        assert_eq!(run("print(-9223372036854775807..9223372036854775807 step 4)\n"), Ok("4611686018427387904\n".to_string()));
+        assert!(run("print(-9223372036854775807..9223372036854775807)\n").is_err());
+    }
+
+    #[test]
+    fn maps_keep_their_keys_in_order() {
+        let source = "\
+let m = {\"b\": 2, \"a\": 1}
+m[\"c\"] = 3
+m[\"a\"] += 10
+print(m, m.keys(), m.values(), m.contains(\"b\"), m.contains(\"z\"))
+for k in m:
+    print(k, m[k])
+";
+        assert_eq!(run(source), Ok("{\"a\": 11, \"b\": 2, \"c\": 3} [\"a\", \"b\", \"c\"] [11, 2, 3] true false\na 11\nb 2\nc 3\n".to_string()));
+        assert_eq!(run("let m = {1: 2}\nprint(m[2])\n"), Err("Key 2 not found in map".to_string()));
+    }
+
+    #[test]
+    fn sets_hold_each_element_once() {
+        let source = "let s = {3, 1, 2, 1}\nprint(s, s.length, s.contains(2), s.contains(5))\nfor x in s:\n    print(x)\n";
+        assert_eq!(run(source), Ok("{1, 2, 3} 3 true false\n1\n2\n3\n".to_string()));
+    }
+
+    #[test]
+    fn struct_fields_are_read_and_assigned() {
+        let source = "\
+struct Matrix:
+    rows: Int
+    cols: Int
+    data: Array
+let m = Matrix { cols: 2, rows: 2, data: [1, 2, 3, 4] }
+let alias = m
+alias.rows = 3
+m.data[0] = 9
+print(m.rows, m.cols, m.data)
+";
+        assert_eq!(run(source), Ok("3 2 [9, 2, 3, 4]\n".to_string()));
+    }
+
+    #[test]
+    fn struct_literals_name_every_field_they_declare() {
+        let source = "struct P:\n    x: Int\n    y: Int\nlet p = P { x: 1, z: 2 }\n";
+        assert_eq!(run(source), Err("P has no field 'z'".to_string()));
+        let source = "struct P:\n    x: Int\nlet p = P { x: 1 }\nprint(p.y)\n";
+        assert_eq!(run(source), Err("P has no field 'y'".to_string()));
+    }
+
+    #[test]
+    fn methods_dispatch_on_the_receiver() {
+        let source = "\
+struct Matrix:
+    rows: Int
+    cols: Int
+    data: Array
+impl Matrix:
+    fn row(self, i):
+        return self.data[i * self.cols..(i + 1) * self.cols]
+    fn grow(self):
+        self.rows += 1
+let m = Matrix { rows: 2, cols: 2, data: [1, 2, 3, 4] }
+m.grow()
+print(m.row(1), m.rows)
+";
+        assert_eq!(run(source), Ok("[3, 4] 3\n".to_string()));
+    }
+
+    #[test]
+    fn builtin_types_have_methods_too() {
+        let source = "let a = [3, 1]\na.push(2)\nprint(\"a,b\".split(\",\"), \"hi\".upper(), a.pop(), a.length)\n";
+        assert_eq!(run(source), Ok("[\"a\", \"b\"] HI 2 2\n".to_string()));
+        assert_eq!(run("print(\"hi\".nope(1))\n"), Err("String has no method 'nope' taking (Int)".to_string()));
+    }
+
+    #[test]
+    fn match_takes_the_first_arm_that_fits() {
+        let source = "\
+enum Shape: Circle(Float), Rect(Float, Float), Empty
+fn area(s):
+    return match s:
+        Circle(r) if r < 0.0 => 0.0
+        Circle(r) => 3.0 * r * r
+        Rect(w, h) => w * h
+        Empty => 0.0
+print(area(Shape.Circle(1.0)), area(Shape.Circle(-1.0)), area(Shape.Rect(2.0, 3.0)), area(Shape.Empty))
+";
+        assert_eq!(run(source), Ok("3.0 0.0 6.0 0.0\n".to_string()));
+    }
+
+    #[test]
+    fn enum_values_fit_enum_typed_fields() {
+        let source = "enum Color: Red, Green\nstruct Pixel: color: Color\nlet p = Pixel { color: Color.Green }\nprint(p.color)\n";
+        assert_eq!(run(source), Ok("Color.Green\n".to_string()));
+    }
+
+    #[test]
+    fn closures_capture_their_scope_by_reference() {
+        let source = "\
+fn counter():
+    let mut n = 0
+    return fn() => n += 1
+let next = counter()
+let other = counter()
+next()
+next()
+print(next(), other())
+";
+        assert_eq!(run(source), Ok("3 1\n".to_string()));
+    }
+
+    #[test]
+    fn functions_are_values() {
+        let source = "\
+fn apply(f, x):
+    return f(x)
+fn compose(f, g):
+    return fn(x) => f(g(x))
+let inc = fn(x) => x + 1
+let double = fn(x: Int) => x * 2
+print(apply(inc, 1), compose(inc, double)(5))
+";
+        assert_eq!(run(source), Ok("2 11\n".to_string()));
+    }
+
+    #[test]
+    fn tuples_return_several_values_and_destructure() {
+        let source = "\
+fn divmod(a, b):
+    return (a / b, a % b)
+let (q, r) = divmod(17, 5)
+let t = (1, \"x\", (true, 2.5))
+let (_, s, (flag, f)) = t
+print(q, r, t, t[1], s, flag, f)
+";
+        assert_eq!(run(source), Ok("3 2 (1, \"x\", (true, 2.5)) x x true 2.5\n".to_string()));
+    }
+
+    #[test]
+    fn destructuring_needs_a_matching_shape() {
+        assert_eq!(run("let (a, b) = (1, 2, 3)\n"), Err("Pattern `(a, b)` does not match (1, 2, 3)".to_string()));
+    }
+
+    #[test]
+    fn ints_promote_to_floats_when_mixed() {
+        assert_eq!(run("print(7 / 2, 7.0 / 2, 1 + 0.5, 10 / 4.0, 2 ** -1)\n"), Ok("3 3.5 1.5 2.5 0.5\n".to_string()));
+    }
+
+    #[test]
+    fn int_overflow_stops_the_program() {
+        assert_eq!(run("print(9223372036854775807 + 1)\n"), Err("Int overflow in 9223372036854775807 + 1".to_string()));
+    }
+
+    #[test]
+    fn annotations_give_numbers_their_sized_type() {
+        let source = "let x: Int32 = 5\nlet u: UInt32 = 0\nlet f: Float32 = 0.1\nprint(x + 1, u - 1, f)\n";
+        assert_eq!(run(source), Ok("6 4294967295 0.1\n".to_string()));
+        assert_eq!(run("let x: UInt32 = -1\n"), Err("-1 does not fit in UInt32".to_string()));
+    }
+
+    #[test]
+    fn only_let_mut_variables_are_reassigned() {
+        assert_eq!(run("let mut x = 1\nx = 2\nprint(x)\n"), Ok("2\n".to_string()));
+        assert!(run("let x = 1\nx = 2\n").is_err_and(|e| e.contains("immutable variable 'x'")));
     }
 }
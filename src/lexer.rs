@@ -16,7 +16,12 @@
         Token::Number(5),
     }
 */
-use crate::token::Token;
+use crate::token::{Span, SpannedToken, StringPart, Token};
+use std::collections::VecDeque;
+use std::fmt;
+
+// A tab advances indentation to the next multiple of this many columns
+const TAB_WIDTH: usize = 4;
 //S1: Defining tokens
 
 // #[derive(Debug, Clone, PartialEq)]
@@ -76,12 +81,68 @@ use crate::token::Token;
 //     EOF,
 // }
 
+// What went wrong while scanning
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexErrorKind {
+    UnexpectedChar(char),
+    UnterminatedString,
+    UnterminatedComment, // `/*` without a matching `*/`
+    InvalidEscape(String), // the escape sequence as written, without the backslash
+    UnterminatedInterpolation,
+    InconsistentIndentation, // dedent to a width that no enclosing block uses
+    IntegerOverflow(String), // the literal as written
+    InvalidNumber(String),   // malformed numeric literal, e.g. `0x` or `1.5i32`
+}
+
+// A lexical error and the span of the offending text
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+impl fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexErrorKind::UnexpectedChar(ch) => write!(f, "unexpected character '{}'", ch),
+            LexErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            LexErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+            LexErrorKind::InvalidEscape(seq) => write!(f, "invalid escape sequence '\\{}'", seq),
+            LexErrorKind::UnterminatedInterpolation => write!(f, "unterminated '${{' in string literal"),
+            LexErrorKind::InconsistentIndentation => {
+                write!(f, "unindent does not match any outer indentation level")
+            }
+            LexErrorKind::IntegerOverflow(literal) => {
+                write!(f, "integer literal '{}' is out of range for its type", literal)
+            }
+            LexErrorKind::InvalidNumber(literal) => write!(f, "invalid numeric literal '{}'", literal),
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.kind)
+    }
+}
+
 // Lexer Structure
 // This part would be scanning the code (as a whole) and then we'll be converting it into tokens in form of vectors
 // one thing to be noted that the typical lexer does not store the tokens in a vector, but rather generates them on the fly as they are needed by the parser.
 pub struct Lexer {
     input: Vec<char>, //tokens are extracted in loop then are append here
     position: usize,  //tells where in the source am I currently, and then the  advance() is called to move forward, This also ensures that the lexer does not process same character twice and also to keep it in bound
+    byte_offset: usize, // byte offset of `position`, so spans can index into the original &str
+    line: usize,        // 1-based line of `position`
+    column: usize,      // 1-based column (in chars) of `position`
+
+    // Layout state. Blocks are delimited by indentation (like Python), so the lexer turns
+    // line breaks into NEWLINE tokens and indentation changes into INDENT/DEDENT tokens.
+    indent_stack: Vec<usize>,        // indentation widths of the open blocks, innermost last
+    pending: VecDeque<SpannedToken>, // INDENT/DEDENT tokens waiting to be handed out
+    at_line_start: bool,             // indentation of the next line has not been measured yet
+    line_has_tokens: bool,           // a NEWLINE is owed for the current logical line
+    nesting: usize,                  // open ( [ { and ${ -- line breaks inside them are ignored
 }
 
 impl Lexer{
@@ -89,6 +150,39 @@ impl Lexer{
         Lexer{
             input: input.chars().collect(),
             position: 0,
+            byte_offset: 0,
+            line: 1,
+            column: 1,
+            indent_stack: vec![0],
+            pending: VecDeque::new(),
+            at_line_start: true,
+            line_has_tokens: false,
+            nesting: 0,
+        }
+    }
+
+    // Lex the whole input. The returned vector always ends with an EOF token whose
+    // span points at the end of the input, so the parser can report errors there too.
+    // Scanning carries on past bad characters so every lexical error is reported at once.
+    pub fn tokenize(&mut self) -> Result<Vec<SpannedToken>, Vec<LexError>> {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            match self.next_token() {
+                Ok(token) => {
+                    let done = token.token == Token::EOF;
+                    tokens.push(token);
+                    if done {
+                        break;
+                    }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
         }
     }
 
@@ -105,34 +199,80 @@ impl Lexer{
         self.input.get(self.position + n).cloned()
     }
 
-    //move forward
+    //move forward, keeping the line/column bookkeeping in sync
     fn advance(&mut self) -> Option<char>{
         let ch = self.peek();
         self.position += 1;
+        if let Some(c) = ch {
+            self.byte_offset += c.len_utf8();
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
         ch
     }
 
-    fn skip_whitespace(&mut self){
+    // Skips whitespace and comments, so the next token starts exactly at `position`.
+    // Line breaks are significant outside brackets, so they are left for next_token.
+    // `///` doc comments are tokens and are not skipped.
+    fn skip_whitespace(&mut self) -> Result<(), LexError> {
         while let Some(ch) = self.peek(){
-            if ch.is_whitespace() {
+            if ch == '\n' && self.nesting == 0 {
+                break;
+            }else if ch.is_whitespace() {
                 self.advance();
+            }else if self.at_line_comment() {
+                self.skip_comment();
+            }else if self.at_block_comment() {
+                self.skip_block_comment()?;
             }else{
                 break;
             }
         }
+        Ok(())
     }
 
-    // Handle double dot for range operators (0..10)
-    fn handle_range(&mut self) -> Token {
-        self.advance(); // consume the first dot
-        if self.peek() == Some('.') {
-            self.advance(); // consume the second dot
-            Token::Range
-        } else {
-            Token::Dot
+    // `//` starts a comment, except `///` which is a doc comment (`////` is a comment again)
+    fn at_line_comment(&self) -> bool {
+        self.peek() == Some('/') && self.peek_next() == Some('/') && !self.at_doc_comment()
+    }
+
+    fn at_doc_comment(&self) -> bool {
+        self.peek() == Some('/')
+            && self.peek_next() == Some('/')
+            && self.peek_nth(2) == Some('/')
+            && self.peek_nth(3) != Some('/')
+    }
+
+    fn at_block_comment(&self) -> bool {
+        self.peek() == Some('/') && self.peek_next() == Some('*')
+    }
+
+    // Skips a `/* ... */` comment. Block comments nest, so `/* a /* b */ c */` is one comment.
+    fn skip_block_comment(&mut self) -> Result<(), LexError> {
+        let start = self.here();
+        let mut depth = 0;
+        loop {
+            if self.at_block_comment() {
+                self.advance();
+                self.advance();
+                depth += 1;
+            } else if self.peek() == Some('*') && self.peek_next() == Some('/') {
+                self.advance();
+                self.advance();
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            } else if self.advance().is_none() {
+                return Err(self.error(LexErrorKind::UnterminatedComment, start));
+            }
         }
     }
-    
+
     fn skip_comment(&mut self) {
         // Skip until the end of the line or end of file
         while let Some(ch) = self.peek() {
@@ -144,9 +284,127 @@ impl Lexer{
     }
 
     //tokenizer logic
-    pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
-        match self.advance(){
+    pub fn next_token(&mut self) -> Result<SpannedToken, LexError> {
+        if let Some(token) = self.pending.pop_front() {
+            return Ok(token);
+        }
+        if self.at_line_start && self.nesting == 0 {
+            self.at_line_start = false;
+            self.measure_indentation()?;
+            if let Some(token) = self.pending.pop_front() {
+                return Ok(token);
+            }
+        }
+
+        self.skip_whitespace()?;
+        let start = self.here();
+        match self.peek() {
+            Some('\n') if self.nesting == 0 => {
+                self.advance();
+                self.at_line_start = true;
+                if self.line_has_tokens {
+                    self.line_has_tokens = false;
+                    return Ok(SpannedToken { token: Token::Newline, span: self.span_from(start) });
+                }
+                return self.next_token();
+            }
+            None if self.nesting == 0 => {
+                // Close the last line and every open block before handing out EOF
+                if self.line_has_tokens {
+                    self.line_has_tokens = false;
+                    return Ok(SpannedToken { token: Token::Newline, span: start });
+                }
+                if self.indent_stack.len() > 1 {
+                    self.indent_stack.pop();
+                    return Ok(SpannedToken { token: Token::Dedent, span: start });
+                }
+            }
+            _ => {}
+        }
+
+        let token = self.scan_token(start)?;
+        self.line_has_tokens = true;
+        match token {
+            Token::Lparen | Token::Lsquare | Token::LBrace => self.nesting += 1,
+            Token::Rparen | Token::Rsquare | Token::RBrace => self.nesting = self.nesting.saturating_sub(1),
+            _ => {}
+        }
+        Ok(SpannedToken {
+            token,
+            span: self.span_from(start),
+        })
+    }
+
+    // Reads the indentation of a new line and queues the INDENT/DEDENT tokens it implies.
+    // Blank and comment-only lines are skipped entirely and never change the indentation.
+    fn measure_indentation(&mut self) -> Result<(), LexError> {
+        loop {
+            let start = self.here();
+            let mut width = 0;
+            loop {
+                match self.peek() {
+                    Some(' ') => width += 1,
+                    Some('\t') => width += TAB_WIDTH - width % TAB_WIDTH,
+                    Some('\r') => {}
+                    // A block comment in the indentation takes up no width
+                    Some('/') if self.at_block_comment() => {
+                        self.skip_block_comment()?;
+                        continue;
+                    }
+                    _ => break,
+                }
+                self.advance();
+            }
+
+            match self.peek() {
+                Some('\n') => {
+                    self.advance();
+                    continue;
+                }
+                _ if self.at_line_comment() => {
+                    self.skip_comment();
+                    continue;
+                }
+                None => return Ok(()), // next_token closes the open blocks at EOF
+                _ => {}
+            }
+
+            let span = self.span_from(start);
+            let current = *self.indent_stack.last().unwrap();
+            if width > current {
+                self.indent_stack.push(width);
+                self.pending.push_back(SpannedToken { token: Token::Indent, span });
+            } else {
+                while width < *self.indent_stack.last().unwrap() {
+                    self.indent_stack.pop();
+                    self.pending.push_back(SpannedToken { token: Token::Dedent, span });
+                }
+                if width != *self.indent_stack.last().unwrap() {
+                    return Err(self.error(LexErrorKind::InconsistentIndentation, start));
+                }
+            }
+            return Ok(());
+        }
+    }
+
+    // Empty span at the current position
+    fn here(&self) -> Span {
+        Span::new(self.byte_offset, self.byte_offset, self.line, self.column)
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        Span::new(start.start, self.byte_offset, start.line, start.column)
+    }
+
+    fn error(&self, kind: LexErrorKind, start: Span) -> LexError {
+        LexError {
+            kind,
+            span: self.span_from(start),
+        }
+    }
+
+    fn scan_token(&mut self, start: Span) -> Result<Token, LexError> {
+        let token = match self.advance(){
 
             Some('=') => {
                 if self.peek() == Some('='){
@@ -164,6 +422,9 @@ impl Lexer{
                 if self.peek() == Some('>'){
                     self.advance();
                     Token::Arrow
+                }else if self.peek() == Some('='){
+                    self.advance();
+                    Token::MinusEq
                 }else {
                     Token::Minus
                 }
@@ -174,7 +435,7 @@ impl Lexer{
                     self.advance();
                     Token::NE
                 }else{
-                    Token::EOF
+                    Token::Bang
                 }
             }
 
@@ -182,6 +443,14 @@ impl Lexer{
                 if self.peek() == Some('='){
                     self.advance();
                     Token::GE
+                }else if self.peek() == Some('>'){
+                    self.advance();
+                    if self.peek() == Some('='){
+                        self.advance();
+                        Token::ShrEq
+                    }else{
+                        Token::Shr
+                    }
                 }else{
                     Token::GT
                 }
@@ -190,28 +459,97 @@ impl Lexer{
                 if self.peek() == Some('='){
                     self.advance();
                     Token::LE
+                }else if self.peek() == Some('<'){
+                    self.advance();
+                    if self.peek() == Some('='){
+                        self.advance();
+                        Token::ShlEq
+                    }else{
+                        Token::Shl
+                    }
                 }else{
                     Token::LT
                 }
             }
 
-            Some('+') => Token::Plus,
-            Some('*') => Token::Star,
+            Some('&') => {
+                if self.peek() == Some('&'){
+                    self.advance();
+                    Token::AndAnd
+                }else if self.peek() == Some('='){
+                    self.advance();
+                    Token::AmpEq
+                }else{
+                    Token::Amp
+                }
+            }
+            Some('|') => {
+                if self.peek() == Some('|'){
+                    self.advance();
+                    Token::OrOr
+                }else if self.peek() == Some('='){
+                    self.advance();
+                    Token::PipeEq
+                }else{
+                    Token::Pipe
+                }
+            }
+
+            Some('+') => {
+                if self.peek() == Some('='){
+                    self.advance();
+                    Token::PlusEq
+                }else{
+                    Token::Plus
+                }
+            }
+            Some('*') => {
+                if self.peek() == Some('*'){
+                    self.advance();
+                    if self.peek() == Some('='){
+                        self.advance();
+                        Token::StarStarEq
+                    }else{
+                        Token::StarStar
+                    }
+                }else if self.peek() == Some('='){
+                    self.advance();
+                    Token::StarEq
+                }else{
+                    Token::Star
+                }
+            }
+            Some('%') => {
+                if self.peek() == Some('='){
+                    self.advance();
+                    Token::PercentEq
+                }else{
+                    Token::Percent
+                }
+            }
+            Some('^') => {
+                if self.peek() == Some('='){
+                    self.advance();
+                    Token::CaretEq
+                }else{
+                    Token::Caret
+                }
+            }
+            // Plain `//` comments are already consumed by skip_whitespace, so `//` here is `///`
+            Some('/') if self.peek() == Some('/') => self.scan_doc_comment(),
             Some('/') => {
-                // Check for comments (// ...)
-                if self.peek() == Some('/') {
-                    self.advance(); // consume the second '/'
-                    self.skip_comment();
-                    return self.next_token(); // Get the next token after the comment
-                } else {
+                if self.peek() == Some('='){
+                    self.advance();
+                    Token::SlashEq
+                }else{
                     Token::Slash
                 }
-            },
+            }
             Some(':') => Token::Colon,
             Some('.') => {
                 if self.peek() == Some('.') {
                     self.advance(); // consume the second dot
-                    Token::Range
+                    Token::DotDot
                 } else {
                     Token::Dot
                 }
@@ -224,45 +562,9 @@ impl Lexer{
             Some('[') => Token::Lsquare,
             Some(']') => Token::Rsquare,
 
-            Some('"') => {
-                let mut s = String::new();
-                while let Some(c) = self.peek() {
-                    if c == '"' {
-                        self.advance();
-                        break;
-                    }else{
-                        s.push(self.advance().unwrap());
-                    }
-                }
-                Token::String(s)
-            }
+            Some('"') => self.scan_string(start)?,
 
-            Some(ch) if ch.is_ascii_digit() => {
-                let mut number = ch.to_string();
-                let mut is_float = false;
-
-                while let Some(c) = self.peek(){
-                    if c.is_ascii_digit(){
-                        number.push(self.advance().unwrap());
-                    }else if c == '.' && !is_float {
-                        // Check if this is a range operator (..) not a decimal point
-                        if self.peek_next() == Some('.') {
-                            // This is a range operator, don't consume the dot
-                            break;
-                        }
-                        is_float = true;
-                        number.push(self.advance().unwrap());
-                    }else{
-                        break;
-                    }
-                }
-
-                if is_float{
-                    Token::Floating(number.parse::<f64>().unwrap())
-                }else{
-                    Token::Number(number.parse::<i64>().unwrap())
-                }
-            }
+            Some(ch) if ch.is_ascii_digit() => self.scan_number(ch, start)?,
 
             Some('@') => {
                 // Handle annotation tokens separately
@@ -297,10 +599,17 @@ impl Lexer{
                 "then" => Token::Then,
                 "else" => Token::Else,
                 "for" => Token::For,
+                "while" => Token::While,
+                "break" => Token::Break,
+                "continue" => Token::Continue,
                 "in" => Token::In,
                 "range" => Token::Range,
                 "fn" => Token::Fn,
                 "return" => Token::Return,
+                "struct" => Token::Struct,
+                "impl" => Token::Impl,
+                "enum" => Token::Enum,
+                "match" => Token::Match,
                 "true" => Token::Boolean(true),
                 "false" => Token::Boolean(false),
                 "branch" => Token::Branch,
@@ -310,8 +619,419 @@ impl Lexer{
                 _ => Token::Identifier(ident),
             }
         }
-        Some(_) => Token::EOF, // Unknown char
+        Some(ch) => return Err(self.error(LexErrorKind::UnexpectedChar(ch), start)),
         None => Token::EOF,
+    };
+        Ok(token)
+    }
+
+    // String literal body, after the opening quote. Resolves escape sequences and splits
+    // `${expr}` interpolations out into their own token lists.
+    fn scan_string(&mut self, start: Span) -> Result<Token, LexError> {
+        let mut parts = Vec::new();
+        let mut s = String::new();
+        // A bad escape is reported once the whole literal is consumed, so lexing resumes after it
+        let mut bad_escape = None;
+        loop {
+            let escape_start = self.here();
+            match self.advance() {
+                Some('"') => break,
+                Some('\\') => match self.scan_escape(escape_start) {
+                    Ok(c) => s.push(c),
+                    Err(e) => {
+                        bad_escape.get_or_insert(e);
+                    }
+                },
+                Some('$') if self.peek() == Some('{') => {
+                    self.advance(); // consume '{'
+                    if !s.is_empty() {
+                        parts.push(StringPart::Literal(std::mem::take(&mut s)));
+                    }
+                    parts.push(StringPart::Code(self.scan_interpolation(escape_start)?));
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error(LexErrorKind::UnterminatedString, start)),
+            }
+        }
+
+        if let Some(e) = bad_escape {
+            return Err(e);
+        }
+        if parts.is_empty() {
+            return Ok(Token::String(s));
+        }
+        if !s.is_empty() {
+            parts.push(StringPart::Literal(s));
+        }
+        Ok(Token::InterpolatedString(parts))
     }
+
+    // Escape sequence after a backslash: \n \t \r \0 \\ \" \' \$ and \u{XXXX}
+    fn scan_escape(&mut self, start: Span) -> Result<char, LexError> {
+        let ch = match self.advance() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('$') => '$',
+            Some('u') => {
+                let mut seq = "u".to_string();
+                if self.peek() != Some('{') {
+                    return Err(self.error(LexErrorKind::InvalidEscape(seq), start));
+                }
+                seq.push(self.advance().unwrap());
+                while let Some(c) = self.peek() {
+                    if c == '}' || c == '"' || seq.len() > 8 {
+                        break;
+                    }
+                    seq.push(self.advance().unwrap());
+                }
+                if self.peek() != Some('}') {
+                    return Err(self.error(LexErrorKind::InvalidEscape(seq), start));
+                }
+                seq.push(self.advance().unwrap());
+                u32::from_str_radix(&seq[2..seq.len() - 1], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error(LexErrorKind::InvalidEscape(seq.clone()), start))?
+            }
+            Some(c) => return Err(self.error(LexErrorKind::InvalidEscape(c.to_string()), start)),
+            None => return Err(self.error(LexErrorKind::UnterminatedString, start)),
+        };
+        Ok(ch)
+    }
+
+    // Tokens of a `${...}` interpolation, up to the matching `}`. The closing brace is
+    // replaced by an EOF token so the parser can treat the list as a standalone expression.
+    fn scan_interpolation(&mut self, start: Span) -> Result<Vec<SpannedToken>, LexError> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        self.nesting += 1;
+        loop {
+            let token = match self.next_token() {
+                Ok(token) => token,
+                Err(e) => {
+                    self.nesting = self.nesting.saturating_sub(1);
+                    return Err(e);
+                }
+            };
+            match token.token {
+                Token::LBrace => depth += 1,
+                Token::RBrace if depth == 0 => {
+                    // next_token already dropped the nesting level for this brace
+                    tokens.push(SpannedToken {
+                        token: Token::EOF,
+                        span: token.span,
+                    });
+                    return Ok(tokens);
+                }
+                Token::RBrace => depth -= 1,
+                Token::EOF => {
+                    self.nesting = self.nesting.saturating_sub(1);
+                    return Err(self.error(LexErrorKind::UnterminatedInterpolation, start));
+                }
+                _ => {}
+            }
+            tokens.push(token);
+        }
+    }
+
+    // Numeric literal starting with `first`. Accepts `_` digit separators, hex/binary/octal
+    // integers (`0xFF`, `0b1010`, `0o17`), decimal floats with exponents (`6.02e23`) and a
+    // type suffix (`i32`, `u32`, `i64`, `f32`, `f64`) that selects the sized token variant.
+    fn scan_number(&mut self, first: char, start: Span) -> Result<Token, LexError> {
+        let mut literal = first.to_string();
+
+        let radix = match (first, self.peek()) {
+            ('0', Some('x' | 'X')) => 16,
+            ('0', Some('b' | 'B')) => 2,
+            ('0', Some('o' | 'O')) => 8,
+            _ => 10,
+        };
+
+        let mut digits = String::new();
+        let mut is_float = false;
+        if radix == 10 {
+            digits.push(first);
+            self.scan_digits(10, &mut digits, &mut literal);
+
+            // A fraction needs a digit after the dot, so `0..10` and `x.0` style uses stay intact
+            if self.peek() == Some('.') && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+                is_float = true;
+                digits.push('.');
+                literal.push(self.advance().unwrap());
+                self.scan_digits(10, &mut digits, &mut literal);
+            }
+
+            if matches!(self.peek(), Some('e' | 'E')) {
+                let sign = matches!(self.peek_next(), Some('+' | '-'));
+                let exponent_digit = self.peek_nth(if sign { 2 } else { 1 });
+                if exponent_digit.is_some_and(|c| c.is_ascii_digit()) {
+                    is_float = true;
+                    digits.push('e');
+                    literal.push(self.advance().unwrap());
+                    if sign {
+                        let c = self.advance().unwrap();
+                        digits.push(c);
+                        literal.push(c);
+                    }
+                    self.scan_digits(10, &mut digits, &mut literal);
+                }
+            }
+        } else {
+            literal.push(self.advance().unwrap()); // the x/b/o marker
+            self.scan_digits(radix, &mut digits, &mut literal);
+            if digits.is_empty() {
+                return Err(self.error(LexErrorKind::InvalidNumber(literal), start));
+            }
+        }
+
+        // A separator goes between two digits: `1_000`, but not `1_`, `1__0` or `1_.5`
+        let chars: Vec<char> = literal.chars().collect();
+        let separated = chars.iter().enumerate().filter(|(_, c)| **c == '_').all(|(i, _)| {
+            let is_digit = |c: Option<&char>| c.is_some_and(|c| c.is_digit(radix));
+            is_digit(i.checked_sub(1).and_then(|before| chars.get(before))) && is_digit(chars.get(i + 1))
+        });
+        if !separated {
+            return Err(self.error(LexErrorKind::InvalidNumber(literal), start));
+        }
+
+        // Optional type suffix, written directly after the digits
+        let mut suffix = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                suffix.push(self.advance().unwrap());
+            } else {
+                break;
+            }
+        }
+        literal.push_str(&suffix);
+
+        let invalid = |lexer: &Self, literal: String| Err(lexer.error(LexErrorKind::InvalidNumber(literal), start));
+        let overflow = |lexer: &Self, literal: String| Err(lexer.error(LexErrorKind::IntegerOverflow(literal), start));
+
+        match suffix.as_str() {
+            "f32" | "f64" if radix == 10 => {
+                let value = match digits.parse::<f64>() {
+                    Ok(v) => v,
+                    Err(_) => return invalid(self, literal),
+                };
+                if suffix == "f32" {
+                    Ok(Token::Float32(value as f32))
+                } else {
+                    Ok(Token::Floating(value))
+                }
+            }
+            "" if is_float => match digits.parse::<f64>() {
+                Ok(v) => Ok(Token::Floating(v)),
+                Err(_) => invalid(self, literal),
+            },
+            _ if is_float => invalid(self, literal),
+            "" | "i64" => match i64::from_str_radix(&digits, radix) {
+                Ok(n) => Ok(Token::Number(n)),
+                Err(_) => overflow(self, literal),
+            },
+            "i32" => match i32::from_str_radix(&digits, radix) {
+                Ok(n) => Ok(Token::Int32(n)),
+                Err(_) => overflow(self, literal),
+            },
+            "u32" => match u32::from_str_radix(&digits, radix) {
+                Ok(n) => Ok(Token::UInt32(n)),
+                Err(_) => overflow(self, literal),
+            },
+            _ => invalid(self, literal),
+        }
+    }
+
+    // Digits valid in `radix`, skipping `_` separators. `digits` gets the bare digits for
+    // parsing, `literal` the text as written for error messages.
+    fn scan_digits(&mut self, radix: u32, digits: &mut String, literal: &mut String) {
+        while let Some(c) = self.peek() {
+            if c == '_' {
+                literal.push(self.advance().unwrap());
+            } else if c.is_digit(radix) {
+                digits.push(c);
+                literal.push(self.advance().unwrap());
+            } else {
+                break;
+            }
+        }
+    }
+
+    // Text of a `///` doc comment, after the first slash. One leading space is dropped so
+    // `/// Adds two numbers` documents "Adds two numbers".
+    fn scan_doc_comment(&mut self) -> Token {
+        self.advance();
+        self.advance();
+        if self.peek() == Some(' ') {
+            self.advance();
+        }
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            text.push(self.advance().unwrap());
+        }
+        Token::DocComment(text.trim_end().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(source: &str) -> Vec<LexErrorKind> {
+        let errors = Lexer::new(source).tokenize().expect_err("source should not lex");
+        errors.into_iter().map(|e| e.kind).collect()
+    }
+
+    fn tokens(source: &str) -> Vec<Token> {
+        let tokens = Lexer::new(source).tokenize().expect("source should lex");
+        tokens.into_iter().map(|t| t.token).collect()
+    }
+
+    fn ident(name: &str) -> Token {
+        Token::Identifier(name.to_string())
+    }
+
+    #[test]
+    fn tokens_carry_their_line_column_and_offsets() {
+        let tokens = Lexer::new("let x = 1\nlet yy = 42\n").tokenize().expect("source should lex");
+        let number = tokens.iter().find(|t| t.token == Token::Number(42)).expect("42 should be a token");
+        assert_eq!((number.span.line, number.span.column), (2, 10));
+        assert_eq!((number.span.start, number.span.end), (19, 21));
+    }
+
+    #[test]
+    fn every_error_is_reported() {
+        assert_eq!(
+            errors("let a = 1 $ 2\nlet b = \"open\n"),
+            vec![LexErrorKind::UnexpectedChar('$'), LexErrorKind::UnterminatedString]
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_text() {
+        let errors = Lexer::new("let a = 1\nlet b = ?\n").tokenize().expect_err("source should not lex");
+        assert_eq!((errors[0].span.line, errors[0].span.column), (2, 9));
+        assert_eq!(errors[0].kind.to_string(), "unexpected character '?'");
+    }
+
+    #[test]
+    fn escapes_are_resolved() {
+        assert_eq!(tokens(r#""a\tb\n\"q\" \\ \$ \u{e9}""#)[0], Token::String("a\tb\n\"q\" \\ $ é".to_string()));
+        assert_eq!(errors(r#""\q""#), vec![LexErrorKind::InvalidEscape("q".to_string())]);
+        assert_eq!(errors(r#""\u{110000}""#), vec![LexErrorKind::InvalidEscape("u{110000}".to_string())]);
+    }
+
+    #[test]
+    fn interpolations_hold_the_tokens_of_their_expression() {
+        let Token::InterpolatedString(parts) = &tokens(r#""x = ${x + 1}!""#)[0] else {
+            panic!("expected an interpolated string");
+        };
+        let [StringPart::Literal(before), StringPart::Code(code), StringPart::Literal(after)] = &parts[..] else {
+            panic!("expected text, code and text, got {:?}", parts);
+        };
+        let code: Vec<Token> = code.iter().map(|t| t.token.clone()).collect();
+        assert_eq!((before.as_str(), after.as_str()), ("x = ", "!"));
+        assert_eq!(code, vec![Token::Identifier("x".to_string()), Token::Plus, Token::Number(1), Token::EOF]);
+        assert_eq!(errors("\"${x"), vec![LexErrorKind::UnterminatedInterpolation]);
+    }
+
+    #[test]
+    fn indented_blocks_open_and_close() {
+        use Token::*;
+        assert_eq!(
+            tokens("if x:\n    y\n        z\nw"),
+            vec![
+                If, ident("x"), Colon, Newline,
+                Indent, ident("y"), Newline,
+                Indent, ident("z"), Newline,
+                Dedent, Dedent, ident("w"), Newline, EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn blocks_still_open_at_the_end_are_closed() {
+        use Token::*;
+        assert_eq!(tokens("while x:\n    y"), vec![While, ident("x"), Colon, Newline, Indent, ident("y"), Newline, Dedent, EOF]);
+    }
+
+    #[test]
+    fn blank_and_comment_lines_do_not_change_indentation() {
+        use Token::*;
+        assert_eq!(
+            tokens("if x:\n\n    // note\n    y\n"),
+            vec![If, ident("x"), Colon, Newline, Indent, ident("y"), Newline, Dedent, EOF]
+        );
+    }
+
+    #[test]
+    fn line_breaks_inside_brackets_are_ignored() {
+        use Token::*;
+        assert_eq!(
+            tokens("f(1,\n      2)\n"),
+            vec![ident("f"), Lparen, Number(1), Comma, Number(2), Rparen, Newline, EOF]
+        );
+    }
+
+    #[test]
+    fn dedent_to_an_unknown_width_is_an_error() {
+        assert_eq!(errors("if x:\n        y\n    z\n"), vec![LexErrorKind::InconsistentIndentation]);
+    }
+
+    #[test]
+    fn numeric_literals() {
+        use Token::*;
+        assert_eq!(
+            tokens("0xFF 0b101 0o17 1_000 2.5e3 7i32 7u32 1.5f32"),
+            vec![Number(255), Number(5), Number(15), Number(1000), Floating(2500.0), Int32(7), UInt32(7), Float32(1.5), Newline, EOF]
+        );
+    }
+
+    #[test]
+    fn ranges_and_fields_are_not_fractions() {
+        use Token::*;
+        assert_eq!(tokens("0..10"), vec![Number(0), DotDot, Number(10), Newline, EOF]);
+        assert_eq!(tokens("t.0"), vec![ident("t"), Dot, Number(0), Newline, EOF]);
+    }
+
+    #[test]
+    fn digit_separators_go_between_digits() {
+        for literal in ["1_", "1__0", "1_.5", "0x_1"] {
+            assert_eq!(errors(literal), vec![LexErrorKind::InvalidNumber(literal.to_string())], "{}", literal);
+        }
+    }
+
+    #[test]
+    fn malformed_literals_are_errors() {
+        for literal in ["0x", "1.5i32", "12abc"] {
+            assert_eq!(errors(literal), vec![LexErrorKind::InvalidNumber(literal.to_string())], "{}", literal);
+        }
+    }
+
+    #[test]
+    fn literals_out_of_range_overflow() {
+        assert_eq!(errors("9223372036854775808"), vec![LexErrorKind::IntegerOverflow("9223372036854775808".to_string())]);
+        assert_eq!(errors("3000000000i32"), vec![LexErrorKind::IntegerOverflow("3000000000i32".to_string())]);
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        use Token::*;
+        assert_eq!(tokens("a /* b /* c */ d */ e"), vec![ident("a"), ident("e"), Newline, EOF]);
+        assert_eq!(errors("a /* b /* c */"), vec![LexErrorKind::UnterminatedComment]);
+    }
+
+    #[test]
+    fn only_three_slashes_start_a_doc_comment() {
+        use Token::*;
+        assert_eq!(tokens("/// Adds\nx"), vec![DocComment("Adds".to_string()), Newline, ident("x"), Newline, EOF]);
+        assert_eq!(tokens("//// not a doc\nx"), vec![ident("x"), Newline, EOF]);
     }
 }
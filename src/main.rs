@@ -2,17 +2,22 @@
 // VORTEX: main.rs
 
 mod ast;
+mod builtins;
+mod checker;
 mod gpu_runtime;
 mod interpreter;
 mod lexer;
+mod numeric;
 mod parser;
 mod repl;
 mod token;
+mod typeck;
 
+use checker::Checker;
 use interpreter::Interpreter;
 use lexer::Lexer;
 use parser::Parser;
-use token::Token;
+use typeck::TypeChecker;
 use std::env;
 use std::fs;
 use std::process;
@@ -28,6 +33,13 @@ fn main() {
                 process::exit(1);
             }
         }
+        3 if args[1] == "--types" => {
+            // Print the inferred types of a file's bindings, for editors to show on hover
+            if let Err(e) = show_types(&args[2]) {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        }
         2 => {
             // One argument - execute file
             let filename = &args[1];
@@ -50,6 +62,7 @@ fn print_usage(program_name: &str) {
     println!("Usage:");
     println!("  {}              Start interactive REPL", program_name);
     println!("  {} <file.vx>    Execute Vortex file", program_name);
+    println!("  {} --types <file.vx>    Show the inferred type of each binding", program_name);
     println!();
     println!("Examples:");
     println!("  {}              # Interactive mode", program_name);
@@ -70,36 +83,68 @@ fn execute_file(filename: &str) -> Result<(), Box<dyn std::error::Error>> {
 
     // Step 1: Lexing
     let mut lexer = Lexer::new(&source);
-    let mut tokens = Vec::new();
-
-    loop {
-        let token = lexer.next_token();
-        if token == Token::EOF {
-            break;
+    let tokens = match lexer.tokenize() {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            for e in &errors {
+                eprintln!("{}:{}: lexical error: {}", filename, e.span, e.kind);
+            }
+            return Err(format!("{} lexical error(s), nothing was executed", errors.len()).into());
         }
-        tokens.push(token);
-    }
+    };
 
-    println!("Tokens generated: {}", tokens.len());
+    println!("Tokens generated: {}", tokens.len() - 1); // not counting EOF
 
     println!("\n========================= PARSING ============================");
 
     // Step 2: Parsing
     let mut parser = Parser::new(tokens);
-    let program = parser.parse();
+    let program = match parser.parse() {
+        Ok(program) => program,
+        Err(errors) => {
+            for e in &errors {
+                eprintln!("{}:{}: syntax error: {}", filename, e.span, e.kind);
+            }
+            return Err(format!("{} syntax error(s), nothing was executed", errors.len()).into());
+        }
+    };
 
     println!("\nAbstract Syntax Tree (AST):");
     for (i, stmt) in program.iter().enumerate() {
         println!("Statement {}: {:?}", i, stmt);
     }
 
+    // Step 3: Static checks
+    let errors = Checker::new().check(&program);
+    if !errors.is_empty() {
+        for e in &errors {
+            eprintln!("{}:{}: error: {}", filename, e.span, e.message);
+        }
+        return Err(format!("{} error(s), nothing was executed", errors.len()).into());
+    }
+
+    // Step 4: Type checking
+    let errors = TypeChecker::new().check(&program);
+    if !errors.is_empty() {
+        for e in &errors {
+            eprintln!("{}:{}: type error: {}", filename, e.span, e.message);
+        }
+        return Err(format!("{} type error(s), nothing was executed", errors.len()).into());
+    }
+
     println!("\n====================== INTERPRETATION ========================");
     
-    // Step 3: Interpret
+    // Step 5: Interpret
     let mut interpreter = Interpreter::new();
     match interpreter.interpret(program) {
         Ok(_) => println!("\nExecution completed successfully."),
-        Err(e) => return Err(format!("Runtime error: {}", e).into()),
+        Err(e) => {
+            return Err(match e.span {
+                Some(span) => format!("{}:{}: runtime error: {}", filename, span, e.message),
+                None => format!("Runtime error: {}", e.message),
+            }
+            .into())
+        }
     }
     
     println!("\n=============================================================");
@@ -108,3 +153,32 @@ fn execute_file(filename: &str) -> Result<(), Box<dyn std::error::Error>> {
     
     Ok(())
 }
+
+// `file:line:column: name: Type` for every variable, parameter and function the file binds
+fn show_types(filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let source = fs::read_to_string(filename)?;
+    let tokens = Lexer::new(&source).tokenize().map_err(|errors| {
+        let first = &errors[0];
+        format!("{}:{}: lexical error: {}", filename, first.span, first.kind)
+    })?;
+    let program = Parser::new(tokens).parse().map_err(|errors| {
+        let first = &errors[0];
+        format!("{}:{}: syntax error: {}", filename, first.span, first.kind)
+    })?;
+    if let Some(e) = Checker::new().check(&program).first() {
+        return Err(format!("{}:{}: error: {}", filename, e.span, e.message).into());
+    }
+
+    let mut type_checker = TypeChecker::new();
+    let errors = type_checker.check(&program);
+    for e in &errors {
+        eprintln!("{}:{}: type error: {}", filename, e.span, e.message);
+    }
+    if !errors.is_empty() {
+        return Err(format!("{} type error(s)", errors.len()).into());
+    }
+    for binding in type_checker.bindings() {
+        println!("{}:{}: {}: {}", filename, binding.span, binding.name, binding.ty);
+    }
+    Ok(())
+}
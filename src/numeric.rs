@@ -0,0 +1,349 @@
+// || shree ganesh ||
+// Sized numeric types: conversions between them and the arithmetic rules of each
+
+use crate::interpreter::Value;
+use crate::token::Token;
+use std::cmp::Ordering;
+
+// `Int` and `Float` are the 64-bit defaults, also spelled `Int64` and `Float64`.
+// `Int` and `Int32` arithmetic is checked: overflow is a runtime error. `UInt32` wraps
+// around modulo 2^32, as unsigned arithmetic does in GPU kernels. `Float32` rounds every
+// result to single precision, so CPU results match what a kernel computes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericType {
+    Int32,
+    UInt32,
+    Int,
+    Float32,
+    Float,
+}
+
+impl NumericType {
+    // The type a `: Type` annotation names, if it is numeric
+    pub fn from_name(name: &str) -> Option<NumericType> {
+        match name {
+            "Int32" => Some(NumericType::Int32),
+            "UInt32" => Some(NumericType::UInt32),
+            "Int" | "Int64" => Some(NumericType::Int),
+            "Float32" => Some(NumericType::Float32),
+            "Float" | "Float64" => Some(NumericType::Float),
+            _ => None,
+        }
+    }
+
+    // The type a conversion function (`i32(x)`) or literal suffix (`5i32`) names
+    pub fn from_suffix(suffix: &str) -> Option<NumericType> {
+        match suffix {
+            "i32" => Some(NumericType::Int32),
+            "u32" => Some(NumericType::UInt32),
+            "i64" => Some(NumericType::Int),
+            "f32" => Some(NumericType::Float32),
+            "f64" => Some(NumericType::Float),
+            _ => None,
+        }
+    }
+
+    pub fn of(value: &Value) -> Option<NumericType> {
+        match value {
+            Value::Int32(_) => Some(NumericType::Int32),
+            Value::UInt32(_) => Some(NumericType::UInt32),
+            Value::Number(_) => Some(NumericType::Int),
+            Value::Float32(_) => Some(NumericType::Float32),
+            Value::Floating(_) => Some(NumericType::Float),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NumericType::Int32 => "Int32",
+            NumericType::UInt32 => "UInt32",
+            NumericType::Int => "Int",
+            NumericType::Float32 => "Float32",
+            NumericType::Float => "Float",
+        }
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, NumericType::Float32 | NumericType::Float)
+    }
+
+    // The type both operands of an arithmetic operator are brought to. A plain Int gives
+    // way to a sized integer, so `x + 1` keeps the type of `x`; an integer meeting a
+    // float becomes a float, and a Float32 meeting a Float is widened, never the Float
+    // narrowed. Two different sized integer types must be converted explicitly.
+    pub fn common(left: NumericType, right: NumericType) -> Result<NumericType, String> {
+        use NumericType::*;
+        match (left, right) {
+            (a, b) if a == b => Ok(a),
+            (Int, other) | (other, Int) if !other.is_float() => Ok(other),
+            (Float, _) | (_, Float) => Ok(Float),
+            (Float32, _) | (_, Float32) => Ok(Float32),
+            (a, b) => Err(format!(
+                "Mismatched integer types {} and {}; convert one with i32(x), u32(x) or i64(x)",
+                a.name(),
+                b.name()
+            )),
+        }
+    }
+
+    // Stores an exact integer result, wrapping for `UInt32` and failing if it does not fit
+    // a checked type
+    fn narrow(self, n: i128) -> Option<Value> {
+        match self {
+            NumericType::Int32 => i32::try_from(n).ok().map(Value::Int32),
+            NumericType::UInt32 => Some(Value::UInt32(n as u32)),
+            NumericType::Int => i64::try_from(n).ok().map(Value::Number),
+            NumericType::Float32 => Some(Value::Float32(n as f32)),
+            NumericType::Float => Some(Value::Floating(n as f64)),
+        }
+    }
+
+    // Like `narrow`, but drops the bits that do not fit whatever the type: shifts and
+    // bitwise operations work on the bit pattern
+    fn truncate(self, n: i128) -> Value {
+        match self {
+            NumericType::Int32 => Value::Int32(n as i32),
+            NumericType::UInt32 => Value::UInt32(n as u32),
+            _ => Value::Number(n as i64),
+        }
+    }
+
+    fn bits(self) -> i128 {
+        match self {
+            NumericType::Int32 | NumericType::UInt32 | NumericType::Float32 => 32,
+            NumericType::Int | NumericType::Float => 64,
+        }
+    }
+}
+
+// The exact value of an integer of any size
+pub fn as_integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::Int32(n) => Some(*n as i128),
+        Value::UInt32(n) => Some(*n as i128),
+        Value::Number(n) => Some(*n as i128),
+        _ => None,
+    }
+}
+
+// An integer of any size as an `i64`, for indices, counts and range bounds
+pub fn as_i64(value: &Value) -> Option<i64> {
+    as_integer(value).map(|n| n as i64)
+}
+
+pub fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float32(f) => Some(*f as f64),
+        Value::Floating(f) => Some(*f),
+        other => as_integer(other).map(|n| n as f64),
+    }
+}
+
+// `i32(x)`, `f32(x)` and the others. Floats convert to integers by truncating toward zero;
+// a value the target type cannot hold is an error rather than a wrapped result.
+pub fn convert(value: &Value, to: NumericType) -> Result<Value, String> {
+    let doesnt_fit = || format!("{} does not fit in {}", value, to.name());
+    match (as_integer(value), as_f64(value)) {
+        (_, None) => Err(format!("Cannot convert {} to {}", value.type_name(), to.name())),
+        (Some(n), _) if !to.is_float() => match to {
+            NumericType::UInt32 => u32::try_from(n).map(Value::UInt32).map_err(|_| doesnt_fit()),
+            _ => to.narrow(n).ok_or_else(doesnt_fit),
+        },
+        (_, Some(f)) => match to {
+            NumericType::Float32 => Ok(Value::Float32(f as f32)),
+            NumericType::Float => Ok(Value::Floating(f)),
+            // NaN and anything outside the range of an Int fits no integer type
+            _ if (i64::MIN as f64..i64::MAX as f64).contains(&f.trunc()) => {
+                convert(&Value::Number(f.trunc() as i64), to).map_err(|_| doesnt_fit())
+            }
+            _ => Err(doesnt_fit()),
+        },
+    }
+}
+
+// The value a `let x: Type` or parameter annotation stores: numbers take the annotated
+// type, but a Float is never silently truncated to an integer type
+pub fn coerce(value: &Value, to: NumericType) -> Result<Value, String> {
+    match NumericType::of(value) {
+        Some(from) if from == to => Ok(value.clone()),
+        Some(from) if from.is_float() && !to.is_float() => {
+            Err(format!("Expected {}, found {}; convert it explicitly", to.name(), from.name()))
+        }
+        Some(_) => convert(value, to),
+        None => Err(format!("Expected {}, found {}", to.name(), value.type_name())),
+    }
+}
+
+pub fn is_zero(value: &Value) -> bool {
+    as_f64(value) == Some(0.0)
+}
+
+// `+ - * / %` on two numbers. Integer operations are exact before the result is stored,
+// so overflow is detected (or wrapped, for `UInt32`) consistently.
+pub fn arithmetic(op: &Token, left: &Value, right: &Value) -> Result<Value, String> {
+    let symbol = match op {
+        Token::Plus => "+",
+        Token::Minus => "-",
+        Token::Star => "*",
+        Token::Slash => "/",
+        _ => "%",
+    };
+    let (Some(l), Some(r)) = (NumericType::of(left), NumericType::of(right)) else {
+        return Err(format!("Invalid '{}' operands: {} and {}", symbol, left.type_name(), right.type_name()));
+    };
+    let ty = NumericType::common(l, r)?;
+    if matches!(op, Token::Slash | Token::Percent) && is_zero(right) {
+        return Err(if *op == Token::Slash { "Division by zero" } else { "Modulo by zero" }.to_string());
+    }
+
+    if ty.is_float() {
+        let (a, b) = (as_f64(left).unwrap_or_default(), as_f64(right).unwrap_or_default());
+        let result = match op {
+            Token::Plus => a + b,
+            Token::Minus => a - b,
+            Token::Star => a * b,
+            Token::Slash => a / b,
+            _ => a % b,
+        };
+        // Rounding the exact f64 result of an f32 operation gives the f32 result
+        return Ok(if ty == NumericType::Float32 { Value::Float32(result as f32) } else { Value::Floating(result) });
+    }
+
+    let (a, b) = (as_integer(left).unwrap_or_default(), as_integer(right).unwrap_or_default());
+    // Int / Int truncates toward zero, like Rust
+    let result = match op {
+        Token::Plus => a + b,
+        Token::Minus => a - b,
+        Token::Star => a * b,
+        Token::Slash => a / b,
+        _ => a % b,
+    };
+    ty.narrow(result)
+        .ok_or_else(|| format!("{} overflow in {} {} {}", ty.name(), left, symbol, right))
+}
+
+// `**`. A negative integer exponent has no integer result, so it gives a Float.
+pub fn power(left: &Value, right: &Value) -> Result<Value, String> {
+    let (Some(l), Some(r)) = (NumericType::of(left), NumericType::of(right)) else {
+        return Err("Invalid '**' operands".to_string());
+    };
+    let ty = NumericType::common(l, r)?;
+    let (a, b) = (as_f64(left).unwrap_or_default(), as_f64(right).unwrap_or_default());
+    match (ty, as_integer(left), as_integer(right)) {
+        (NumericType::Float32, _, _) => Ok(Value::Float32((a as f32).powf(b as f32))),
+        (NumericType::Float, _, _) => Ok(Value::Floating(a.powf(b))),
+        (_, Some(base), Some(exp)) if exp >= 0 => {
+            let exp = u32::try_from(exp).map_err(|_| format!("Exponent {} is too large", exp))?;
+            let overflow = || format!("{} overflow in {} ** {}", ty.name(), left, right);
+            match ty {
+                NumericType::UInt32 => Ok(Value::UInt32((base as u32).wrapping_pow(exp))),
+                _ => base.checked_pow(exp).and_then(|n| ty.narrow(n)).ok_or_else(overflow),
+            }
+        }
+        _ => Ok(Value::Floating(a.powf(b))),
+    }
+}
+
+pub fn negate(value: &Value) -> Result<Value, String> {
+    match value {
+        Value::Number(n) => n.checked_neg().map(Value::Number).ok_or_else(|| format!("Int overflow in -({})", n)),
+        Value::Int32(n) => n.checked_neg().map(Value::Int32).ok_or_else(|| format!("Int32 overflow in -({})", n)),
+        Value::UInt32(n) => Ok(Value::UInt32(n.wrapping_neg())),
+        Value::Float32(f) => Ok(Value::Float32(-f)),
+        Value::Floating(f) => Ok(Value::Floating(-f)),
+        _ => Err("Invalid unary minus".to_string()),
+    }
+}
+
+// `& | ^ << >>` on integers of the same size (or one of them a plain Int). Shifts must
+// stay below the width of the type; bits shifted out are dropped.
+pub fn bitwise(op: &Token, left: &Value, right: &Value) -> Result<Value, String> {
+    let (Some(a), Some(b)) = (as_integer(left), as_integer(right)) else {
+        return Err(match op {
+            Token::Shl | Token::Shr => "Shift operands must be integers",
+            _ => "Bitwise operands must both be integers or both be booleans",
+        }
+        .to_string());
+    };
+    let (l, r) = (NumericType::of(left), NumericType::of(right));
+    let ty = match op {
+        // The shift amount does not affect the type of the result
+        Token::Shl | Token::Shr => l.unwrap_or(NumericType::Int),
+        _ => NumericType::common(l.unwrap_or(NumericType::Int), r.unwrap_or(NumericType::Int))?,
+    };
+    let result = match op {
+        Token::Amp => a & b,
+        Token::Pipe => a | b,
+        Token::Caret => a ^ b,
+        _ if !(0..ty.bits()).contains(&b) => {
+            return Err(format!("Shift amount {} is out of range 0..{}", b, ty.bits()));
+        }
+        Token::Shl => a << b,
+        _ => a >> b,
+    };
+    Ok(ty.truncate(result))
+}
+
+// Numbers of any types compare by their exact values; `None` if either is NaN
+pub fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (as_integer(left), as_integer(right)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        _ => as_f64(left)?.partial_cmp(&as_f64(right)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn int_overflow_is_an_error() {
+        let max = Value::Number(i64::MAX);
+        assert_eq!(arithmetic(&Token::Plus, &max, &Value::Number(1)), Err("Int overflow in 9223372036854775807 + 1".to_string()));
+        assert!(arithmetic(&Token::Star, &max, &Value::Number(2)).is_err());
+        assert!(arithmetic(&Token::Minus, &Value::Number(i64::MIN), &Value::Number(1)).is_err());
+        assert!(negate(&Value::Number(i64::MIN)).is_err());
+        assert!(power(&Value::Number(2), &Value::Number(63)).is_err());
+        assert_eq!(power(&Value::Number(2), &Value::Number(62)), Ok(Value::Number(1 << 62)));
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        assert_eq!(arithmetic(&Token::Slash, &Value::Number(1), &Value::Number(0)), Err("Division by zero".to_string()));
+        assert_eq!(arithmetic(&Token::Percent, &Value::Number(1), &Value::Number(0)), Err("Modulo by zero".to_string()));
+    }
+
+    #[test]
+    fn int32_overflow_is_an_error_and_uint32_wraps() {
+        assert!(arithmetic(&Token::Plus, &Value::Int32(i32::MAX), &Value::Number(1)).is_err());
+        assert_eq!(arithmetic(&Token::Minus, &Value::UInt32(0), &Value::Number(1)), Ok(Value::UInt32(u32::MAX)));
+        assert_eq!(arithmetic(&Token::Star, &Value::UInt32(1 << 31), &Value::UInt32(2)), Ok(Value::UInt32(0)));
+        assert_eq!(arithmetic(&Token::Percent, &Value::Int32(1), &Value::Int32(0)), Err("Modulo by zero".to_string()));
+    }
+
+    #[test]
+    fn a_plain_int_takes_the_sized_type() {
+        assert_eq!(arithmetic(&Token::Plus, &Value::Int32(1), &Value::Number(2)), Ok(Value::Int32(3)));
+        assert_eq!(NumericType::common(NumericType::Int, NumericType::UInt32), Ok(NumericType::UInt32));
+        assert!(NumericType::common(NumericType::Int32, NumericType::UInt32).is_err());
+    }
+
+    #[test]
+    fn floats_widen_and_never_narrow() {
+        use NumericType::*;
+        assert_eq!(NumericType::common(Int, Float32), Ok(Float32));
+        assert_eq!(NumericType::common(Float32, Float), Ok(Float));
+        assert_eq!(NumericType::common(Float, Float32), Ok(Float));
+        assert_eq!(arithmetic(&Token::Plus, &Value::Float32(0.5), &Value::Floating(0.1)), Ok(Value::Floating(0.6)));
+    }
+
+    #[test]
+    fn conversions_fail_on_values_that_do_not_fit() {
+        assert_eq!(convert(&Value::Floating(-3.9), NumericType::Int32), Ok(Value::Int32(-3)));
+        assert_eq!(convert(&Value::Number(-1), NumericType::UInt32), Err("-1 does not fit in UInt32".to_string()));
+        assert!(convert(&Value::Floating(f64::NAN), NumericType::Int).is_err());
+        assert!(convert(&Value::Floating(1e19), NumericType::Int).is_err());
+        assert!(coerce(&Value::Floating(1.5), NumericType::Int).is_err());
+    }
+}
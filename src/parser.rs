@@ -1,66 +1,255 @@
 // || shree ganesh ||
 // Parser for the Vortex language
 
-use crate::ast::{Expr, Stmt};
-use crate::token::Token;
+use crate::ast::{Expr, ExprKind, MatchArm, Param, Pattern, PatternKind, Stmt, StmtKind, StringSegment};
+use crate::token::{Span, SpannedToken, StringPart, Token};
+use std::fmt;
+
+// What the parser could not make sense of
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    // `expected` describes what the grammar allows here, e.g. "`:`" or "expression"
+    Expected { expected: String, found: Token },
+    InvalidAssignmentTarget,
+    FallbackWithoutBranch, // `fallback` not preceded by any `branch` arm
+    LoopControlOutsideLoop(Token),   // `break` or `continue` with no loop around it
+    LoopControlOutOfParallel(Token), // `break` or `continue` that would leave a `parallel` body
+    UndefinedLabel(String),
+    DuplicateField(String), // a field named twice in a struct declaration or literal
+    DuplicateVariant(String),
+    ExpectedMethod,         // something other than `fn` inside an `impl` block
+    MissingSelf(String),    // a method whose first parameter is not `self`
+}
+
+// A syntax error and the span of the offending token or expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::Expected { expected, found } => write!(f, "expected {}, found {}", expected, found),
+            ParseErrorKind::InvalidAssignmentTarget => write!(f, "invalid assignment target"),
+            ParseErrorKind::FallbackWithoutBranch => write!(f, "`fallback` without a preceding `branch`"),
+            ParseErrorKind::LoopControlOutsideLoop(keyword) => write!(f, "{} outside of a loop", keyword),
+            ParseErrorKind::LoopControlOutOfParallel(keyword) => {
+                write!(f, "{} cannot jump out of a `parallel` body; every lane runs to completion", keyword)
+            }
+            ParseErrorKind::UndefinedLabel(label) => write!(f, "no enclosing loop is labelled `{}`", label),
+            ParseErrorKind::DuplicateField(name) => write!(f, "field `{}` is given more than once", name),
+            ParseErrorKind::DuplicateVariant(name) => write!(f, "variant `{}` is declared more than once", name),
+            ParseErrorKind::ExpectedMethod => write!(f, "only `fn` definitions are allowed in an `impl` block"),
+            ParseErrorKind::MissingSelf(name) => write!(f, "method `{}` must take `self` as its first parameter", name),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.kind)
+    }
+}
+
+type ParseResult<T> = Result<T, ParseError>;
+
+// A loop enclosing the statement being parsed, for checking `break`/`continue`
+enum LoopScope {
+    Sequential(Option<String>), // `for` or `while`, with its label
+    Parallel,
+}
 
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     current: usize,
+    errors: Vec<ParseError>, // errors from statements that were skipped to recover
+    loops: Vec<LoopScope>,   // enclosing loops of the current function, innermost last
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+    pub fn new(tokens: Vec<SpannedToken>) -> Self {
+        Self { tokens, current: 0, errors: Vec::new(), loops: Vec::new() }
     }
 
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    // Parses the whole program. A statement with a syntax error is skipped up to the next
+    // statement boundary so that one run reports every error; if there were any, the
+    // program is not returned at all.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
         let mut statements = Vec::new();
         while !self.is_at_end() {
-            if let Some(stmt) = self.parse_statement() {
+            // A statement that failed on the last line of its block leaves the DEDENT
+            // closing that block behind
+            if self.match_token(&Token::Newline) || self.match_token(&Token::Dedent) {
+                continue;
+            }
+            if self.check(&Token::Indent) {
+                self.errors.push(self.expected("statement"));
+                self.synchronize();
+                continue;
+            }
+            if let Some(stmt) = self.parse_recovering() {
                 statements.push(stmt);
-            } else {
-                // Skip problematic tokens
-                self.advance();
             }
         }
-        statements
+
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    // One statement and its line ending; on error the error is recorded and the rest of
+    // the statement skipped
+    fn parse_recovering(&mut self) -> Option<Stmt> {
+        match self.parse_statement().and_then(|stmt| self.end_statement().map(|_| stmt)) {
+            Ok(stmt) => Some(stmt),
+            Err(e) => {
+                self.errors.push(e);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // Statements end at a NEWLINE; those ending in an indented block already consumed theirs
+    fn end_statement(&mut self) -> ParseResult<()> {
+        if matches!(self.previous(), Token::Newline | Token::Dedent)
+            || self.match_token(&Token::Newline)
+            || matches!(self.peek(), Token::Dedent | Token::EOF)
+            || self.at_statement_in_open_bracket()
+        {
+            Ok(())
+        } else {
+            Err(self.expected("end of line"))
+        }
+    }
+
+    // Skips the rest of a broken statement: up to and including its NEWLINE, along with
+    // any indented block that belongs to it. Stops before the DEDENT closing the block the
+    // statement is in, and before a statement on a later line when a bracket was left open.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                Token::EOF => return,
+                _ if depth == 0 && self.at_statement_in_open_bracket() => return,
+                Token::Indent => depth += 1,
+                Token::Dedent if depth == 0 => return,
+                Token::Dedent => {
+                    depth -= 1;
+                    if depth == 0 {
+                        // End of the broken statement's own block
+                        self.advance();
+                        return;
+                    }
+                }
+                Token::Newline if depth == 0 => {
+                    self.advance();
+                    if !self.check(&Token::Indent) {
+                        return;
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    // The lexer ignores line breaks inside brackets, so after a bracket left open the
+    // following lines come without NEWLINEs. A keyword only a statement can start with,
+    // first on a line whose break was swallowed, still marks where the next statement
+    // begins. `fn` only counts with a name after it, as `fn(x) => ...` is a lambda.
+    fn at_statement_in_open_bracket(&self) -> bool {
+        let starts_statement = match self.peek() {
+            Token::Let | Token::If | Token::For | Token::While | Token::Break | Token::Continue |
+            Token::Parallel | Token::Branch | Token::Struct | Token::Impl | Token::Enum |
+            Token::GPU | Token::Return | Token::DocComment(_) => true,
+            Token::Fn => self.peek_next() != Some(&Token::Lparen),
+            _ => false,
+        };
+        starts_statement
+            && !matches!(self.previous(), Token::Newline | Token::Indent | Token::Dedent)
+            && self.peek_span().line > self.previous_span().line
     }
 
-    fn parse_statement(&mut self) -> Option<Stmt> {
+    // The body after a `:`. Either an indented block on the following lines, or a single
+    // statement on the same line, as in `if x > 40: print(x)`.
+    // Errors inside an indented block are recovered from within the block.
+    fn parse_block(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        let mut statements = Vec::new();
+        if self.match_token(&Token::Newline) {
+            self.consume(&Token::Indent, "indented block")?;
+            while !matches!(self.peek(), Token::Dedent | Token::EOF) {
+                if self.match_token(&Token::Newline) {
+                    continue;
+                }
+                if let Some(stmt) = self.parse_recovering() {
+                    statements.push(stmt);
+                }
+            }
+            self.match_token(&Token::Dedent);
+        } else {
+            statements.push(self.parse_statement()?);
+        }
+        Ok(Stmt::new(StmtKind::Block(statements), self.span_from(start)))
+    }
+
+    // Whether the statement continues with `token` (e.g. `then`/`else` after an if block).
+    // A single-line block leaves its NEWLINE behind, which is skipped in that case.
+    fn continues_with(&mut self, token: &Token) -> bool {
+        if self.peek() == &Token::Newline && self.peek_next() == Some(token) {
+            self.advance();
+        }
+        self.check(token)
+    }
+
+    fn parse_statement(&mut self) -> ParseResult<Stmt> {
         match self.peek() {
             Token::Let => self.parse_let_statement(),
             Token::If => self.parse_if_statement(),
             Token::Branch => self.parse_branch_statement(),
-            Token::Fallback => self.parse_fallback_statement(),
-            Token::For => self.parse_for_statement(),
+            Token::Fallback => Err(ParseError { kind: ParseErrorKind::FallbackWithoutBranch, span: self.peek_span() }),
+            Token::For => self.parse_for_statement(None),
+            Token::While => self.parse_while_statement(None),
+            Token::Break | Token::Continue => self.parse_loop_control(),
+            Token::Identifier(_) if self.peek_next() == Some(&Token::Colon) => self.parse_labeled_loop(),
             Token::Parallel => self.parse_parallel_statement(),
-            Token::Fn => self.parse_function_statement(false),
+            Token::Fn if self.peek_next() != Some(&Token::Lparen) => self.parse_function_statement(false),
+            Token::Struct => self.parse_struct_statement(),
+            Token::Impl => self.parse_impl_statement(),
+            Token::Enum => self.parse_enum_statement(),
             Token::GPU => {
+                let start = self.peek_span();
                 self.advance(); // consume @gpu
                 if self.peek() == &Token::Fn {
-                    self.parse_function_statement(true)
+                    self.parse_function_statement(true).map(|stmt| Stmt::new(stmt.kind, start.to(stmt.span)))
                 } else {
-                    None
+                    Err(self.expected("`fn` after `@gpu`"))
                 }
             },
             Token::Return => self.parse_return_statement(),
-            Token::Identifier(_) if self.peek_next() == Some(&Token::Lparen) => {
-                Some(Stmt::ExprStmt(self.parse_expression()))
-            },
+            Token::DocComment(_) => self.parse_documented_statement(),
             _ => {
                 if self.is_expression_start() {
-                    Some(Stmt::ExprStmt(self.parse_expression()))
+                    let expr = self.parse_expression()?;
+                    let span = expr.span;
+                    Ok(Stmt::new(StmtKind::ExprStmt(expr), span))
                 } else {
-                    None
+                    Err(self.expected("statement"))
                 }
             }
         }
     }
 
-    fn parse_let_statement(&mut self) -> Option<Stmt> {
+    fn parse_let_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
         self.advance(); // consume 'let'
-        
+
         let mutable = if self.peek() == &Token::Mut {
             self.advance();
             true
@@ -68,238 +257,406 @@ impl Parser {
             false
         };
 
-        let name = match self.advance() {
-            Token::Identifier(id) => id,
-            _ => return None,
+        // `let (a, b) = ...`, `let [x, y] = ...` or `let Point { x, y } = ...`
+        let destructures = match self.peek() {
+            Token::Lparen | Token::Lsquare => true,
+            Token::Identifier(_) => self.peek_next() == Some(&Token::LBrace),
+            _ => false,
         };
+        if destructures {
+            let pattern = self.parse_pattern()?;
+            self.consume(&Token::Equals, "`=`")?;
+            let value = self.parse_expression()?;
+            return Ok(Stmt::new(StmtKind::LetPattern { pattern, value, mutable }, self.span_from(start)));
+        }
+
+        let name = self.consume_identifier("variable name")?;
 
         let type_name = if self.match_token(&Token::Colon) {
-            match self.advance() {
-                Token::Identifier(t) => Some(t),
-                _ => None,
-            }
+            Some(self.parse_type()?)
         } else {
             None
         };
 
-        if !self.match_token(&Token::Equals) {
-            return None;
-        }
+        self.consume(&Token::Equals, "`=`")?;
 
-        let value = self.parse_expression();
+        let value = self.parse_expression()?;
 
-        Some(Stmt::Let {
-            name,
-            type_name,
-            value,
-            mutable,
-        })
+        Ok(Stmt::new(
+            StmtKind::Let {
+                name,
+                type_name,
+                value,
+                mutable,
+                doc: None,
+            },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_if_statement(&mut self) -> Option<Stmt> {
+    // `if c: ... then c2: ... then c3: ... else: ...` with any number of `then` (else-if)
+    // clauses. The chain is nested right to left, so each `then` is the else branch of the
+    // clause before it.
+    fn parse_if_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
         self.advance(); // consume 'if'
-        
-        let condition = self.parse_expression();
-        
-        if !self.match_token(&Token::Colon) {
-            return None;
-        }
 
-        let mut then_statements = Vec::new();
-        while !matches!(self.peek(), Token::Then | Token::Else | Token::EOF | Token::Branch | Token::Fallback | Token::For | Token::Parallel | Token::Fn | Token::GPU | Token::Let) {
-            if let Some(stmt) = self.parse_statement() {
-                then_statements.push(stmt);
-            } else {
-                break;
-            }
+        let condition = self.parse_expression()?;
+        self.consume(&Token::Colon, "`:`")?;
+        let then_branch = Box::new(self.parse_block()?);
+
+        // Handle 'then' (else-if) clauses
+        let mut clauses = Vec::new();
+        while self.continues_with(&Token::Then) {
+            let then_start = self.peek_span();
+            self.advance(); // consume 'then'
+            let clause_condition = self.parse_expression()?;
+            self.consume(&Token::Colon, "`:`")?;
+            let clause_body = self.parse_block()?;
+            clauses.push((then_start, clause_condition, clause_body));
         }
 
-        let then_branch = Box::new(Stmt::Block(then_statements));
+        // Handle 'else'
         let mut else_branch = None;
+        if self.continues_with(&Token::Else) {
+            self.advance(); // consume 'else'
+            self.consume(&Token::Colon, "`:`")?;
+            else_branch = Some(Box::new(self.parse_block()?));
+        }
 
-        // Handle 'then' (else-if)
-        if self.match_token(&Token::Then) {
-            let else_if_condition = self.parse_expression();
-            
-            if !self.match_token(&Token::Colon) {
-                return None;
-            }
-
-            let mut else_if_statements = Vec::new();
-            while !matches!(self.peek(), Token::Else | Token::EOF | Token::Branch | Token::Fallback | Token::For | Token::Parallel | Token::Fn | Token::GPU | Token::Let) {
-                if let Some(stmt) = self.parse_statement() {
-                    else_if_statements.push(stmt);
-                } else {
-                    break;
-                }
-            }
-
-            else_branch = Some(Box::new(Stmt::IfStmt {
-                condition: else_if_condition,
-                then_branch: Box::new(Stmt::Block(else_if_statements)),
-                else_branch: None,
-            }));
+        for (then_start, clause_condition, clause_body) in clauses.into_iter().rev() {
+            let end = else_branch.as_ref().map_or(clause_body.span, |stmt| stmt.span);
+            else_branch = Some(Box::new(Stmt::new(
+                StmtKind::IfStmt {
+                    condition: clause_condition,
+                    then_branch: Box::new(clause_body),
+                    else_branch,
+                },
+                then_start.to(end),
+            )));
         }
 
-        // Handle 'else'
-        if self.match_token(&Token::Else) {
-            if !self.match_token(&Token::Colon) {
-                return None;
-            }
+        Ok(Stmt::new(
+            StmtKind::IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+            },
+            self.span_from(start),
+        ))
+    }
 
-            let mut else_statements = Vec::new();
-            while !matches!(self.peek(), Token::EOF | Token::Branch | Token::Fallback | Token::For | Token::Parallel | Token::Fn | Token::GPU | Token::Let) {
-                if let Some(stmt) = self.parse_statement() {
-                    else_statements.push(stmt);
-                } else {
-                    break;
-                }
-            }
+    // A group of `branch cond => body` arms on consecutive lines, optionally closed by
+    // `fallback => body`. Each body is a statement on the same line or an indented block.
+    fn parse_branch_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        let mut arms = Vec::new();
 
-            if let Some(ref mut existing_else) = else_branch {
-                if let Stmt::IfStmt { ref mut else_branch, .. } = **existing_else {
-                    *else_branch = Some(Box::new(Stmt::Block(else_statements)));
-                }
-            } else {
-                else_branch = Some(Box::new(Stmt::Block(else_statements)));
+        loop {
+            self.advance(); // consume 'branch'
+            let condition = self.parse_expression()?;
+            self.consume(&Token::FatArrow, "`=>`")?;
+            let body = self.parse_block()?;
+            arms.push((condition, body));
+
+            if !self.continues_with(&Token::Branch) {
+                break;
             }
         }
 
-        Some(Stmt::IfStmt {
-            condition,
-            then_branch,
-            else_branch,
-        })
+        let fallback = if self.continues_with(&Token::Fallback) {
+            self.advance(); // consume 'fallback'
+            self.consume(&Token::FatArrow, "`=>`")?;
+            Some(Box::new(self.parse_block()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::new(StmtKind::Branch { arms, fallback }, self.span_from(start)))
     }
 
-    fn parse_branch_statement(&mut self) -> Option<Stmt> {
-        self.advance(); // consume 'branch'
-        
-        let condition = self.parse_expression();
-        
-        if !self.match_token(&Token::FatArrow) {
-            return None;
-        }
+    // `label: for ...` or `label: while ...`
+    fn parse_labeled_loop(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        let label = self.consume_identifier("loop label")?;
+        self.advance(); // consume ':'
 
-        let body = if self.is_expression_start() {
-            Box::new(Stmt::ExprStmt(self.parse_expression()))
-        } else {
-            Box::new(Stmt::Block(vec![]))
+        let stmt = match self.peek() {
+            Token::For => self.parse_for_statement(Some(label))?,
+            Token::While => self.parse_while_statement(Some(label))?,
+            _ => return Err(self.expected("`for` or `while` after a loop label")),
         };
+        Ok(Stmt::new(stmt.kind, start.to(stmt.span)))
+    }
 
-        Some(Stmt::Branch { condition, body })
+    // The body of a loop, parsed with the loop on the scope stack
+    fn parse_loop_body(&mut self, scope: LoopScope) -> ParseResult<Box<Stmt>> {
+        self.loops.push(scope);
+        let body = self.parse_block();
+        self.loops.pop();
+        Ok(Box::new(body?))
     }
 
-    fn parse_fallback_statement(&mut self) -> Option<Stmt> {
-        self.advance(); // consume 'fallback'
-        
-        if !self.match_token(&Token::FatArrow) {
-            return None;
-        }
+    fn parse_while_statement(&mut self, label: Option<String>) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        self.advance(); // consume 'while'
 
-        let body = if self.is_expression_start() {
-            Box::new(Stmt::ExprStmt(self.parse_expression()))
-        } else {
-            Box::new(Stmt::Block(vec![]))
-        };
+        let condition = self.parse_expression()?;
+
+        self.consume(&Token::Colon, "`:`")?;
+
+        let body = self.parse_loop_body(LoopScope::Sequential(label.clone()))?;
 
-        Some(Stmt::Fallback(body))
+        Ok(Stmt::new(StmtKind::While { condition, body, label }, self.span_from(start)))
     }
 
-    fn parse_for_statement(&mut self) -> Option<Stmt> {
-        self.advance(); // consume 'for'
-        
-        let var = match self.advance() {
-            Token::Identifier(id) => id,
-            _ => return None,
+    // `break`/`continue`, optionally naming the loop they apply to. Neither may leave a
+    // `parallel` body, although a plain `continue` may end the current lane.
+    fn parse_loop_control(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        let keyword = self.advance();
+        let label = match self.peek() {
+            Token::Identifier(label) => {
+                let label = label.clone();
+                self.advance();
+                Some(label)
+            }
+            _ => None,
         };
+        let span = self.span_from(start);
+        let error = |kind| Err(ParseError { kind, span });
 
-        if !self.match_token(&Token::In) {
-            return None;
+        if self.loops.is_empty() {
+            return error(ParseErrorKind::LoopControlOutsideLoop(keyword));
         }
-
-        let range = self.parse_expression();
-
-        if !self.match_token(&Token::Colon) {
-            return None;
+        if let Some(label) = &label {
+            let known = self.loops.iter().any(|scope| matches!(scope, LoopScope::Sequential(Some(name)) if name == label));
+            if !known {
+                return error(ParseErrorKind::UndefinedLabel(label.clone()));
+            }
         }
-
-        let mut statements = Vec::new();
-        while !matches!(self.peek(), Token::EOF | Token::Branch | Token::Fallback | Token::For | Token::Parallel | Token::Fn | Token::GPU | Token::Let | Token::If) {
-            if let Some(stmt) = self.parse_statement() {
-                statements.push(stmt);
-            } else {
-                break;
+        let ends_lane = keyword == Token::Continue && label.is_none();
+        for scope in self.loops.iter().rev() {
+            match scope {
+                LoopScope::Parallel if ends_lane => break,
+                LoopScope::Parallel => return error(ParseErrorKind::LoopControlOutOfParallel(keyword)),
+                LoopScope::Sequential(name) if label.is_none() || *name == label => break,
+                LoopScope::Sequential(_) => {}
             }
         }
 
-        let body = Box::new(Stmt::Block(statements));
+        let kind = if keyword == Token::Break { StmtKind::Break(label) } else { StmtKind::Continue(label) };
+        Ok(Stmt::new(kind, span))
+    }
+
+    fn parse_for_statement(&mut self, label: Option<String>) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        self.advance(); // consume 'for'
+
+        let pattern = self.parse_pattern()?;
+
+        self.consume(&Token::In, "`in`")?;
+
+        let range = self.parse_expression()?;
 
-        Some(Stmt::For { var, range, body })
+        self.consume(&Token::Colon, "`:`")?;
+
+        let body = self.parse_loop_body(LoopScope::Sequential(label.clone()))?;
+
+        Ok(Stmt::new(StmtKind::For { pattern, range, body, label }, self.span_from(start)))
     }
 
-    fn parse_parallel_statement(&mut self) -> Option<Stmt> {
+    fn parse_parallel_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
         self.advance(); // consume 'parallel'
-        
-        let var = match self.advance() {
-            Token::Identifier(id) => id,
-            _ => return None,
+
+        let var = self.consume_identifier("loop variable")?;
+
+        self.consume(&Token::In, "`in`")?;
+
+        let range = self.parse_expression()?;
+
+        self.consume(&Token::Colon, "`:`")?;
+
+        let body = self.parse_loop_body(LoopScope::Parallel)?;
+
+        Ok(Stmt::new(StmtKind::Parallel { var, range, body }, self.span_from(start)))
+    }
+
+    fn parse_function_statement(&mut self, gpu: bool) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        self.advance(); // consume 'fn'
+
+        let name = self.consume_identifier("function name")?;
+        let params = self.parse_params()?;
+
+        let return_type = if self.match_token(&Token::Arrow) {
+            Some(self.parse_type()?)
+        } else {
+            None
         };
 
-        if !self.match_token(&Token::In) {
-            return None;
-        }
+        self.consume(&Token::Colon, "`:`")?;
+
+        // Loops around the definition are not loops of the function body
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let body = self.parse_block();
+        self.loops = enclosing_loops;
+        let body = Box::new(body?);
+
+        Ok(Stmt::new(
+            StmtKind::FunctionDef {
+                name,
+                params,
+                return_type,
+                body,
+                gpu,
+                doc: None,
+            },
+            self.span_from(start),
+        ))
+    }
+
+    // `struct Name: field: Type, ...` on one line, or an indented block of `field: Type` lines
+    fn parse_struct_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        self.advance(); // consume 'struct'
 
-        let range = self.parse_expression();
+        let name = self.consume_identifier("struct name")?;
+        self.consume(&Token::Colon, "`:`")?;
+
+        let fields = self.parse_members("fields", ParseErrorKind::DuplicateField, |parser| {
+            let field = parser.consume_identifier("field name")?;
+            parser.consume(&Token::Colon, "`:`")?;
+            Ok((field, parser.parse_type()?))
+        })?;
+
+        Ok(Stmt::new(StmtKind::Struct { name, fields, doc: None }, self.span_from(start)))
+    }
+
+    // `enum Name: Variant(Type, ...), Unit, ...` on one line, or one variant per line
+    fn parse_enum_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        self.advance(); // consume 'enum'
+
+        let name = self.consume_identifier("enum name")?;
+        self.consume(&Token::Colon, "`:`")?;
+
+        let variants = self.parse_members("variants", ParseErrorKind::DuplicateVariant, |parser| {
+            let variant = parser.consume_identifier("variant name")?;
+            let mut payload = Vec::new();
+            if parser.match_token(&Token::Lparen) {
+                while !parser.check(&Token::Rparen) {
+                    payload.push(parser.parse_type()?);
+                    if !parser.match_token(&Token::Comma) {
+                        break;
+                    }
+                }
+                parser.consume(&Token::Rparen, "`,` or `)`")?;
+            }
+            Ok((variant, payload))
+        })?;
+
+        Ok(Stmt::new(StmtKind::Enum { name, variants, doc: None }, self.span_from(start)))
+    }
 
-        if !self.match_token(&Token::Colon) {
-            return None;
+    // The named members of a struct or enum declaration: comma-separated on the line after
+    // the `:`, or an indented block with one member per line
+    fn parse_members<T>(
+        &mut self,
+        what: &str,
+        duplicate: fn(String) -> ParseErrorKind,
+        mut member: impl FnMut(&mut Self) -> ParseResult<(String, T)>,
+    ) -> ParseResult<Vec<(String, T)>> {
+        let mut members: Vec<(String, T)> = Vec::new();
+        let block = self.match_token(&Token::Newline);
+        if block {
+            self.consume(&Token::Indent, &format!("indented block of {}", what))?;
         }
+        loop {
+            let member_span = self.peek_span();
+            let (name, value) = member(self)?;
+            if members.iter().any(|(existing, _)| *existing == name) {
+                return Err(ParseError { kind: duplicate(name), span: member_span });
+            }
+            members.push((name, value));
 
-        let mut statements = Vec::new();
-        while !matches!(self.peek(), Token::EOF | Token::Branch | Token::Fallback | Token::For | Token::Parallel | Token::Fn | Token::GPU | Token::Let | Token::If) {
-            if let Some(stmt) = self.parse_statement() {
-                statements.push(stmt);
-            } else {
+            let separated = self.match_token(&Token::Comma);
+            if block {
+                self.match_token(&Token::Newline);
+                if self.match_token(&Token::Dedent) || self.check(&Token::EOF) {
+                    break;
+                }
+            } else if !separated {
                 break;
             }
         }
+        Ok(members)
+    }
 
-        let body = Box::new(Stmt::Block(statements));
+    // `impl Type:` followed by an indented block of methods. Bad entries are reported and
+    // left out, keeping the rest of the block.
+    fn parse_impl_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        self.advance(); // consume 'impl'
 
-        Some(Stmt::Parallel { var, range, body })
-    }
+        let type_name = self.consume_identifier("type name")?;
+        self.consume(&Token::Colon, "`:`")?;
+        if !self.check(&Token::Newline) {
+            return Err(self.expected("indented block of methods"));
+        }
 
-    fn parse_function_statement(&mut self, gpu: bool) -> Option<Stmt> {
-        self.advance(); // consume 'fn'
-        
-        let name = match self.advance() {
-            Token::Identifier(id) => id,
-            _ => return None,
-        };
+        let body = self.parse_block()?;
+        let mut methods = Vec::new();
+        if let StmtKind::Block(statements) = body.kind {
+            for stmt in statements {
+                match &stmt.kind {
+                    StmtKind::FunctionDef { name, params, .. } => {
+                        if params.first().map(|param| param.name.as_str()) != Some("self") {
+                            self.errors.push(ParseError { kind: ParseErrorKind::MissingSelf(name.clone()), span: stmt.span });
+                        } else {
+                            methods.push(stmt);
+                        }
+                    }
+                    // What is left of a doc comment with nothing after it
+                    StmtKind::Block(empty) if empty.is_empty() => {}
+                    _ => self.errors.push(ParseError { kind: ParseErrorKind::ExpectedMethod, span: stmt.span }),
+                }
+            }
+        }
 
-        if !self.match_token(&Token::Lparen) {
-            return None;
+        Ok(Stmt::new(StmtKind::Impl { type_name, methods }, self.span_from(start)))
+    }
+
+    // A type annotation: a name, optionally behind `&` or `&mut` (`a: &mut Matrix`)
+    fn parse_type(&mut self) -> ParseResult<String> {
+        let mut prefix = String::new();
+        if self.match_token(&Token::Amp) {
+            prefix.push('&');
+            if self.match_token(&Token::Mut) {
+                prefix.push_str("mut ");
+            }
         }
+        Ok(prefix + &self.consume_identifier("type name")?)
+    }
+
+    // `(name: Type, ...)`; parameter types are optional
+    fn parse_params(&mut self) -> ParseResult<Vec<Param>> {
+        self.consume(&Token::Lparen, "`(`")?;
 
         let mut params = Vec::new();
         if self.peek() != &Token::Rparen {
             loop {
-                let param_name = match self.advance() {
-                    Token::Identifier(id) => id,
-                    _ => break,
-                };
+                let start = self.peek_span();
+                let param_name = self.consume_identifier("parameter name")?;
 
                 let param_type = if self.match_token(&Token::Colon) {
-                    match self.advance() {
-                        Token::Identifier(t) => Some(t),
-                        _ => None,
-                    }
+                    Some(self.parse_type()?)
                 } else {
                     None
                 };
 
-                params.push((param_name, param_type));
+                params.push(Param { name: param_name, ty: param_type, span: self.span_from(start) });
 
                 if !self.match_token(&Token::Comma) {
                     break;
@@ -307,243 +664,724 @@ impl Parser {
             }
         }
 
-        if !self.match_token(&Token::Rparen) {
-            return None;
+        self.consume(&Token::Rparen, "`,` or `)`")?;
+        Ok(params)
+    }
+
+    // `fn(params) => expr`, after the `fn`. The body reaches as far as an expression can,
+    // so `fn(x) => x + 1` returns `x + 1`.
+    fn parse_lambda(&mut self, start: Span) -> ParseResult<Expr> {
+        let params = self.parse_params()?;
+        self.consume(&Token::FatArrow, "`=>`")?;
+
+        // Loops around the lambda are not loops of its body
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let body = self.parse_expression();
+        self.loops = enclosing_loops;
+        let body = body?;
+
+        let span = body.span;
+        Ok(Expr::new(
+            ExprKind::Lambda {
+                params,
+                body: Box::new(Stmt::new(StmtKind::Return(body), span)),
+            },
+            self.span_from(start),
+        ))
+    }
+
+    // `///` lines and the statement they document. Docs are kept on function, variable,
+    // struct and enum declarations; on anything else they are dropped like ordinary comments.
+    fn parse_documented_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        let mut lines = Vec::new();
+        while let Token::DocComment(text) = self.peek() {
+            lines.push(text.clone());
+            self.advance();
+            self.match_token(&Token::Newline);
         }
 
-        let return_type = if self.match_token(&Token::Arrow) {
-            match self.advance() {
-                Token::Identifier(t) => Some(t),
-                _ => None,
+        if matches!(self.peek(), Token::Dedent | Token::EOF) {
+            // Nothing left in the block to document
+            return Ok(Stmt::new(StmtKind::Block(vec![]), self.span_from(start)));
+        }
+
+        let mut stmt = self.parse_statement()?;
+        if let StmtKind::FunctionDef { doc, .. }
+        | StmtKind::Let { doc, .. }
+        | StmtKind::Struct { doc, .. }
+        | StmtKind::Enum { doc, .. } = &mut stmt.kind
+        {
+            *doc = Some(lines.join("\n"));
+        }
+        Ok(stmt)
+    }
+
+    fn parse_return_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek_span();
+        self.advance(); // consume 'return'
+        let value = self.parse_expression()?;
+        Ok(Stmt::new(StmtKind::Return(value), self.span_from(start)))
+    }
+
+    fn parse_expression(&mut self) -> ParseResult<Expr> {
+        self.parse_assignment()
+    }
+
+    // Assignment is the loosest, right-associative level: `a = b = 1` assigns both
+    fn parse_assignment(&mut self) -> ParseResult<Expr> {
+        // A `match` ends in an indented block, so it can only be a whole expression: a
+        // statement, or the value of a `let`, assignment or `return`
+        if self.check(&Token::Match) {
+            let start = self.peek_span();
+            self.advance(); // consume 'match'
+            return self.parse_match(start);
+        }
+        let target = self.parse_range()?;
+
+        let compound = self.peek().compound_op();
+        if compound.is_none() && self.peek() != &Token::Equals {
+            return Ok(target);
+        }
+        if !Self::is_assignable(&target) {
+            return Err(ParseError { kind: ParseErrorKind::InvalidAssignmentTarget, span: target.span });
+        }
+        self.advance();
+        let value = self.parse_assignment()?;
+        let span = target.span.to(value.span);
+
+        let kind = match compound {
+            Some(op) => ExprKind::CompoundAssignment {
+                target: Box::new(target),
+                op,
+                value: Box::new(value),
+            },
+            None => ExprKind::Assignment {
+                target: Box::new(target),
+                value: Box::new(value),
+            },
+        };
+        Ok(Expr::new(kind, span))
+    }
+
+    fn is_assignable(target: &Expr) -> bool {
+        matches!(target.kind, ExprKind::Ident(_) | ExprKind::Index { .. } | ExprKind::Field { .. })
+    }
+
+    // `start..end` binds looser than every binary operator, so `0..n + 1` is `0..(n + 1)`.
+    // Either bound may be omitted, and `step n` (a contextual keyword) sets the stride.
+    fn parse_range(&mut self) -> ParseResult<Expr> {
+        let span_start = self.peek_span();
+        let start = if self.check(&Token::DotDot) {
+            None
+        } else {
+            let expr = self.parse_or()?;
+            if !self.check(&Token::DotDot) {
+                return Ok(expr);
             }
+            Some(Box::new(expr))
+        };
+        self.advance(); // consume '..'
+
+        let end = if self.is_expression_start() && !self.at_step(true) {
+            Some(Box::new(self.parse_or()?))
         } else {
             None
         };
+        let step = if self.at_step(false) {
+            self.advance(); // consume 'step'
+            Some(Box::new(self.parse_or()?))
+        } else {
+            None
+        };
+
+        Ok(Expr::new(
+            ExprKind::Range { start, end, step },
+            self.span_from(span_start),
+        ))
+    }
+
+    // `step` is only the keyword when an expression follows it, so `0..step` ends at a
+    // variable named `step`. Right after the `..`, a `-`, `(` or `[` after `step` continues
+    // the variable (`0..step - 1`) rather than starting the step.
+    fn at_step(&self, after_dots: bool) -> bool {
+        let continues_name = |token: &Token| after_dots && matches!(token, Token::Minus | Token::Lparen | Token::Lsquare);
+        matches!(self.peek(), Token::Identifier(word) if word == "step")
+            && self.peek_next().is_some_and(|next| Self::starts_expression(next) && !continues_name(next))
+    }
+
+    fn parse_or(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_and()?;
 
-        if !self.match_token(&Token::Colon) {
-            return None;
+        while matches!(self.peek(), Token::OrOr) {
+            let op = self.advance();
+            let right = self.parse_and()?;
+            expr = Self::binary(expr, op, right);
         }
 
-        let mut statements = Vec::new();
-        while !matches!(self.peek(), Token::EOF | Token::Branch | Token::Fallback | Token::For | Token::Parallel | Token::Fn | Token::GPU | Token::Let | Token::If) {
-            if let Some(stmt) = self.parse_statement() {
-                statements.push(stmt);
-            } else {
-                break;
-            }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_equality()?;
+
+        while matches!(self.peek(), Token::AndAnd) {
+            let op = self.advance();
+            let right = self.parse_equality()?;
+            expr = Self::binary(expr, op, right);
         }
 
-        let body = Box::new(Stmt::Block(statements));
+        Ok(expr)
+    }
 
-        Some(Stmt::FunctionDef {
-            name,
-            params,
-            return_type,
-            body,
-            gpu,
-        })
+    fn parse_equality(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_comparison()?;
+
+        while matches!(self.peek(), Token::EQ | Token::NE) {
+            let op = self.advance();
+            let right = self.parse_comparison()?;
+            expr = Self::binary(expr, op, right);
+        }
+
+        Ok(expr)
     }
 
-    fn parse_return_statement(&mut self) -> Option<Stmt> {
-        self.advance(); // consume 'return'
-        let value = self.parse_expression();
-        Some(Stmt::Return(value))
+    fn parse_comparison(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_bitwise()?;
+
+        while matches!(self.peek(), Token::GT | Token::GE | Token::LT | Token::LE) {
+            let op = self.advance();
+            let right = self.parse_bitwise()?;
+            expr = Self::binary(expr, op, right);
+        }
+
+        Ok(expr)
     }
 
-    fn parse_expression(&mut self) -> Expr {
-        self.parse_comparison()
+    fn parse_bitwise(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_shift()?;
+
+        while matches!(self.peek(), Token::Amp | Token::Pipe | Token::Caret) {
+            let op = self.advance();
+            let right = self.parse_shift()?;
+            expr = Self::binary(expr, op, right);
+        }
+
+        Ok(expr)
     }
 
-    fn parse_comparison(&mut self) -> Expr {
-        let mut expr = self.parse_term();
+    fn parse_shift(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_term()?;
 
-        while matches!(self.peek(), Token::GT | Token::GE | Token::LT | Token::LE | Token::EQ | Token::NE) {
+        while matches!(self.peek(), Token::Shl | Token::Shr) {
             let op = self.advance();
-            let right = self.parse_term();
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op,
-                right: Box::new(right),
-            };
+            let right = self.parse_term()?;
+            expr = Self::binary(expr, op, right);
         }
 
-        expr
+        Ok(expr)
     }
 
-    fn parse_term(&mut self) -> Expr {
-        let mut expr = self.parse_factor();
+    fn parse_term(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_factor()?;
 
         while matches!(self.peek(), Token::Plus | Token::Minus) {
             let op = self.advance();
-            let right = self.parse_factor();
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op,
-                right: Box::new(right),
-            };
+            let right = self.parse_factor()?;
+            expr = Self::binary(expr, op, right);
         }
 
-        expr
+        Ok(expr)
     }
 
-    fn parse_factor(&mut self) -> Expr {
-        let mut expr = self.parse_unary();
+    fn parse_factor(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_power()?;
 
-        while matches!(self.peek(), Token::Star | Token::Slash) {
+        while matches!(self.peek(), Token::Star | Token::Slash | Token::Percent) {
             let op = self.advance();
-            let right = self.parse_unary();
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op,
-                right: Box::new(right),
-            };
+            let right = self.parse_power()?;
+            expr = Self::binary(expr, op, right);
         }
 
-        expr
+        Ok(expr)
     }
 
-    fn parse_unary(&mut self) -> Expr {
-        if matches!(self.peek(), Token::Minus) {
+    // `**` is right-associative and binds looser than unary operators,
+    // so `-2 ** 2` is `(-2) ** 2`.
+    fn parse_power(&mut self) -> ParseResult<Expr> {
+        let expr = self.parse_unary()?;
+
+        if matches!(self.peek(), Token::StarStar) {
             let op = self.advance();
-            let expr = self.parse_unary();
-            return Expr::Unary {
+            let right = self.parse_power()?;
+            return Ok(Self::binary(expr, op, right));
+        }
+
+        Ok(expr)
+    }
+
+    fn binary(left: Expr, op: Token, right: Expr) -> Expr {
+        let span = left.span.to(right.span);
+        Expr::new(
+            ExprKind::Binary {
+                left: Box::new(left),
                 op,
-                expr: Box::new(expr),
-            };
+                right: Box::new(right),
+            },
+            span,
+        )
+    }
+
+    fn parse_unary(&mut self) -> ParseResult<Expr> {
+        if matches!(self.peek(), Token::Minus | Token::Bang) {
+            let start = self.peek_span();
+            let op = self.advance();
+            let expr = self.parse_unary()?;
+            return Ok(Expr::new(
+                ExprKind::Unary {
+                    op,
+                    expr: Box::new(expr),
+                },
+                self.span_from(start),
+            ));
         }
 
         self.parse_call()
     }
 
-    fn parse_call(&mut self) -> Expr {
-        let mut expr = self.parse_primary();
+    // Postfix operations: calls `f(x)`, indexing `a[i]` and field access `a.length`
+    fn parse_call(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_primary()?;
+
+        loop {
+            let start = expr.span;
+            let kind = match self.peek() {
+                Token::Lparen => {
+                    self.advance(); // consume '('
+                    ExprKind::FunctionCall {
+                        callee: Box::new(expr),
+                        arguments: self.parse_arguments()?,
+                    }
+                }
+                Token::Lsquare => {
+                    self.advance(); // consume '['
+                    let index = self.parse_expression()?;
+                    self.consume(&Token::Rsquare, "`]`")?;
+                    ExprKind::Index {
+                        object: Box::new(expr),
+                        index: Box::new(index),
+                    }
+                }
+                Token::Dot => {
+                    self.advance(); // consume '.'
+                    let name = self.consume_identifier("field or method name")?;
+                    if self.match_token(&Token::Lparen) {
+                        let arguments = self.parse_arguments()?;
+                        ExprKind::MethodCall {
+                            receiver: Box::new(expr),
+                            method: name,
+                            arguments,
+                        }
+                    } else {
+                        ExprKind::Field {
+                            object: Box::new(expr),
+                            name,
+                        }
+                    }
+                }
+                _ => break,
+            };
+            expr = Expr::new(kind, self.span_from(start));
+        }
 
-        while self.peek() == &Token::Lparen {
-            self.advance(); // consume '('
-            let mut args = Vec::new();
+        Ok(expr)
+    }
 
-            if self.peek() != &Token::Rparen {
-                args.push(self.parse_expression());
-                while self.match_token(&Token::Comma) {
-                    args.push(self.parse_expression());
-                }
-            }
+    // Call arguments after the `(`, up to and including the `)`
+    fn parse_arguments(&mut self) -> ParseResult<Vec<Expr>> {
+        let mut args = Vec::new();
 
-            if !self.match_token(&Token::Rparen) {
-                // Try to recover by breaking
-                break;
+        if self.peek() != &Token::Rparen {
+            args.push(self.parse_expression()?);
+            while self.match_token(&Token::Comma) {
+                args.push(self.parse_expression()?);
             }
-
-            expr = Expr::FunctionCall {
-                callee: Box::new(expr),
-                arguments: args,
-            };
         }
 
-        expr
+        self.consume(&Token::Rparen, "`,` or `)`")?;
+        Ok(args)
     }
 
-    fn parse_primary(&mut self) -> Expr {
-        match self.advance() {
-            Token::Number(n) => {
-                // Check for range syntax
-                if matches!(self.peek(), Token::Range | Token::Dot) {
-                    self.parse_range_from_start(Expr::Number(n))
-                } else {
-                    Expr::Number(n)
+    fn parse_primary(&mut self) -> ParseResult<Expr> {
+        let start = self.peek_span();
+        if !self.is_expression_start() {
+            return Err(self.expected("expression"));
+        }
+        let expr = match self.advance() {
+            Token::Number(n) => Expr::new(ExprKind::Number(n), start),
+            Token::Floating(f) => Expr::new(ExprKind::Floating(f), start),
+            Token::Int32(n) => Expr::new(ExprKind::Int32(n), start),
+            Token::UInt32(n) => Expr::new(ExprKind::UInt32(n), start),
+            Token::Float32(f) => Expr::new(ExprKind::Float32(f), start),
+            Token::Boolean(b) => Expr::new(ExprKind::Boolean(b), start),
+            Token::String(s) => Expr::new(ExprKind::String(s), start),
+            Token::InterpolatedString(parts) => {
+                let segments = parts
+                    .into_iter()
+                    .map(|part| match part {
+                        StringPart::Literal(text) => Ok(StringSegment::Literal(text)),
+                        StringPart::Code(tokens) => Self::parse_interpolation(tokens).map(StringSegment::Expr),
+                    })
+                    .collect::<ParseResult<_>>()?;
+                Expr::new(ExprKind::InterpolatedString(segments), start)
+            },
+            Token::Identifier(id) if self.check(&Token::LBrace) => self.parse_struct_literal(id, start)?,
+            Token::Identifier(id) => Expr::new(ExprKind::Ident(id), start),
+            Token::Lsquare => {
+                let mut elements = Vec::new();
+                while !self.check(&Token::Rsquare) {
+                    elements.push(self.parse_expression()?);
+                    if !self.match_token(&Token::Comma) {
+                        break;
+                    }
                 }
+                self.consume(&Token::Rsquare, "`,` or `]`")?;
+                Expr::new(ExprKind::Array(elements), self.span_from(start))
             },
-            Token::Floating(f) => {
-                // Check for range syntax
-                if matches!(self.peek(), Token::Range | Token::Dot) {
-                    self.parse_range_from_start(Expr::Floating(f))
+            Token::LBrace => self.parse_map_or_set(start)?,
+            Token::Fn => self.parse_lambda(start)?,
+            Token::Range => {
+                // Handle range(start, end) function
+                if self.match_token(&Token::Lparen) {
+                    let range_start = self.parse_expression()?;
+                    self.consume(&Token::Comma, "`,`")?;
+                    let end = self.parse_expression()?;
+                    self.consume(&Token::Rparen, "`)`")?;
+                    Expr::new(
+                        ExprKind::Range {
+                            start: Some(Box::new(range_start)),
+                            end: Some(Box::new(end)),
+                            step: None,
+                        },
+                        self.span_from(start),
+                    )
                 } else {
-                    Expr::Floating(f)
+                    Expr::new(ExprKind::Ident("range".to_string()), start)
                 }
             },
-            Token::Boolean(b) => Expr::Boolean(b),
-            Token::String(s) => Expr::String(s),
-            Token::Identifier(id) => {
-                // Check for range syntax or function calls
-                if matches!(self.peek(), Token::Range | Token::Dot) {
-                    self.parse_range_from_start(Expr::Ident(id))
+            Token::Lparen => {
+                let expr = self.parse_expression()?;
+                if self.match_token(&Token::Comma) {
+                    // A tuple, e.g. `(q, r)`; the trailing comma makes `(x,)` one too
+                    let mut elements = vec![expr];
+                    while !self.check(&Token::Rparen) {
+                        elements.push(self.parse_expression()?);
+                        if !self.match_token(&Token::Comma) {
+                            break;
+                        }
+                    }
+                    self.consume(&Token::Rparen, "`,` or `)`")?;
+                    Expr::new(ExprKind::Tuple(elements), self.span_from(start))
                 } else {
-                    Expr::Ident(id)
+                    self.consume(&Token::Rparen, "`)`")?;
+                    Expr::new(ExprKind::Grouping(Box::new(expr)), self.span_from(start))
                 }
             },
-            Token::Range => {
-                // Handle range(start, end) function
+            found => {
+                return Err(ParseError {
+                    kind: ParseErrorKind::Expected { expected: "expression".to_string(), found },
+                    span: start,
+                })
+            }
+        };
+        Ok(expr)
+    }
+
+    // `match subject:` and its arms, after the `match`. An arm's body is a statement on the
+    // same line (whose value is the arm's value) or an indented block.
+    fn parse_match(&mut self, start: Span) -> ParseResult<Expr> {
+        let subject = self.parse_expression()?;
+        self.consume(&Token::Colon, "`:`")?;
+        self.consume(&Token::Newline, "end of line")?;
+        self.consume(&Token::Indent, "indented block of match arms")?;
+
+        let mut arms = Vec::new();
+        while !matches!(self.peek(), Token::Dedent | Token::EOF) {
+            if self.match_token(&Token::Newline) {
+                continue;
+            }
+            let pattern = self.parse_pattern()?;
+            let guard = if self.match_token(&Token::If) {
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
+            self.consume(&Token::FatArrow, "`=>`")?;
+            let body = if self.check(&Token::Newline) {
+                self.parse_block()?
+            } else {
+                let stmt = self.parse_statement()?;
+                self.end_statement()?;
+                stmt
+            };
+            arms.push(MatchArm { pattern, guard, body });
+        }
+        self.match_token(&Token::Dedent);
+
+        Ok(Expr::new(
+            ExprKind::Match {
+                subject: Box::new(subject),
+                arms,
+            },
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_pattern(&mut self) -> ParseResult<Pattern> {
+        let start = self.peek_span();
+        let kind = match self.peek().clone() {
+            Token::Identifier(name) if name == "_" => {
+                self.advance();
+                PatternKind::Wildcard
+            }
+            Token::Lparen | Token::Lsquare => {
+                let tuple = self.advance() == Token::Lparen;
+                let close = if tuple { Token::Rparen } else { Token::Rsquare };
+                let mut elements = Vec::new();
+                let mut trailing_comma = false;
+                while !self.check(&close) {
+                    elements.push(self.parse_pattern()?);
+                    trailing_comma = self.match_token(&Token::Comma);
+                    if !trailing_comma {
+                        break;
+                    }
+                }
+                self.consume(&close, if tuple { "`,` or `)`" } else { "`,` or `]`" })?;
+                match elements.pop() {
+                    // `(p)` is just `p` in parentheses
+                    Some(only) if tuple && elements.is_empty() && !trailing_comma => return Ok(only),
+                    Some(last) => elements.push(last),
+                    None => {}
+                }
+                if tuple {
+                    PatternKind::Tuple(elements)
+                } else {
+                    PatternKind::Array(elements)
+                }
+            }
+            Token::Identifier(name) if self.peek_next() == Some(&Token::LBrace) => {
+                self.advance();
+                self.advance(); // consume '{'
+                let mut fields: Vec<(String, Pattern)> = Vec::new();
+                while !self.check(&Token::RBrace) {
+                    let field_span = self.peek_span();
+                    let field = self.consume_identifier("field name")?;
+                    let pattern = if self.match_token(&Token::Colon) {
+                        self.parse_pattern()?
+                    } else {
+                        Pattern { kind: PatternKind::Binding(field.clone()), span: field_span }
+                    };
+                    if fields.iter().any(|(existing, _)| *existing == field) {
+                        return Err(ParseError { kind: ParseErrorKind::DuplicateField(field), span: field_span });
+                    }
+                    fields.push((field, pattern));
+                    if !self.match_token(&Token::Comma) {
+                        break;
+                    }
+                }
+                self.consume(&Token::RBrace, "`,` or `}`")?;
+                PatternKind::Struct { name, fields }
+            }
+            Token::Identifier(name) => {
+                self.advance();
+                let (enum_name, name) = if self.match_token(&Token::Dot) {
+                    (Some(name), self.consume_identifier("variant name")?)
+                } else {
+                    (None, name)
+                };
                 if self.match_token(&Token::Lparen) {
-                    let start = self.parse_expression();
-                    if self.match_token(&Token::Comma) {
-                        let end = self.parse_expression();
-                        if self.match_token(&Token::Rparen) {
-                            Expr::Range {
-                                start: Box::new(start),
-                                end: Box::new(end),
-                            }
-                        } else {
-                            Expr::Ident("error".to_string())
+                    let mut fields = Vec::new();
+                    while !self.check(&Token::Rparen) {
+                        fields.push(self.parse_pattern()?);
+                        if !self.match_token(&Token::Comma) {
+                            break;
                         }
-                    } else {
-                        Expr::Ident("error".to_string())
                     }
+                    self.consume(&Token::Rparen, "`,` or `)`")?;
+                    PatternKind::Variant { enum_name, name, fields }
+                } else if enum_name.is_some() {
+                    PatternKind::Variant { enum_name, name, fields: Vec::new() }
                 } else {
-                    Expr::Ident("range".to_string())
+                    PatternKind::Binding(name)
                 }
-            },
-            Token::Lparen => {
-                let expr = self.parse_expression();
-                if self.match_token(&Token::Rparen) {
-                    Expr::Grouping(Box::new(expr))
+            }
+            _ => {
+                let literal = self.parse_literal_pattern()?;
+                if self.match_token(&Token::DotDot) {
+                    let end = self.parse_literal_pattern()?;
+                    PatternKind::Range { start: literal, end }
                 } else {
-                    expr
+                    PatternKind::Literal(literal)
                 }
-            },
-            _ => Expr::Ident("error".to_string()),
+            }
+        };
+        Ok(Pattern { kind, span: self.span_from(start) })
+    }
+
+    fn parse_literal_pattern(&mut self) -> ParseResult<Expr> {
+        let start = self.peek_span();
+        let negative = self.match_token(&Token::Minus);
+        let kind = match self.peek().clone() {
+            Token::Number(n) => ExprKind::Number(if negative { -n } else { n }),
+            Token::Floating(f) => ExprKind::Floating(if negative { -f } else { f }),
+            Token::String(s) if !negative => ExprKind::String(s),
+            Token::Boolean(b) if !negative => ExprKind::Boolean(b),
+            _ => return Err(self.expected("pattern")),
+        };
+        self.advance();
+        Ok(Expr::new(kind, self.span_from(start)))
+    }
+
+    // `Name { field: value, ... }`, after the name
+    fn parse_struct_literal(&mut self, name: String, start: Span) -> ParseResult<Expr> {
+        self.advance(); // consume '{'
+        let mut fields: Vec<(String, Expr)> = Vec::new();
+        while !self.check(&Token::RBrace) {
+            let field_span = self.peek_span();
+            let field = self.consume_identifier("field name")?;
+            self.consume(&Token::Colon, "`:`")?;
+            let value = self.parse_expression()?;
+            if fields.iter().any(|(existing, _)| *existing == field) {
+                return Err(ParseError { kind: ParseErrorKind::DuplicateField(field), span: field_span });
+            }
+            fields.push((field, value));
+            if !self.match_token(&Token::Comma) {
+                break;
+            }
         }
+        self.consume(&Token::RBrace, "`,` or `}`")?;
+        Ok(Expr::new(ExprKind::StructLiteral { name, fields }, self.span_from(start)))
     }
 
-    fn parse_range_from_start(&mut self, start: Expr) -> Expr {
-        if self.match_token(&Token::Range) {
-            // Handle .. operator
-            let end = self.parse_expression();
-            Expr::Range {
-                start: Box::new(start),
-                end: Box::new(end),
+    // `{k: v, ...}` or `{a, b, ...}`, after the `{`. The first entry decides which it is;
+    // `{}` is an empty map (an empty set is written `set()`).
+    fn parse_map_or_set(&mut self, start: Span) -> ParseResult<Expr> {
+        if self.match_token(&Token::RBrace) {
+            return Ok(Expr::new(ExprKind::Map(Vec::new()), self.span_from(start)));
+        }
+
+        let first = self.parse_expression()?;
+        let kind = if self.match_token(&Token::Colon) {
+            let mut entries = vec![(first, self.parse_expression()?)];
+            while self.match_token(&Token::Comma) && !self.check(&Token::RBrace) {
+                let key = self.parse_expression()?;
+                self.consume(&Token::Colon, "`:`")?;
+                entries.push((key, self.parse_expression()?));
             }
-        } else if self.match_token(&Token::Dot) {
-            // Handle potential .. operator
-            if self.match_token(&Token::Dot) {
-                let end = self.parse_expression();
-                Expr::Range {
-                    start: Box::new(start),
-                    end: Box::new(end),
-                }
+            ExprKind::Map(entries)
+        } else {
+            let mut elements = vec![first];
+            while self.match_token(&Token::Comma) && !self.check(&Token::RBrace) {
+                elements.push(self.parse_expression()?);
+            }
+            ExprKind::Set(elements)
+        };
+        self.consume(&Token::RBrace, "`,` or `}`")?;
+        Ok(Expr::new(kind, self.span_from(start)))
+    }
+
+    // The expression inside `${...}`; the lexer already split out its tokens
+    fn parse_interpolation(tokens: Vec<SpannedToken>) -> ParseResult<Expr> {
+        let mut parser = Parser::new(tokens);
+        let result = parser.parse_expression().and_then(|expr| {
+            if parser.is_at_end() {
+                Ok(expr)
             } else {
-                // Just a single dot - not a range
-                start
+                Err(parser.expected("`}`"))
             }
-        } else {
-            start
-        }
+        });
+        // The lexer ends the embedded tokens with EOF where the closing `}` was
+        result.map_err(|mut e| {
+            if let ParseErrorKind::Expected { found: found @ Token::EOF, .. } = &mut e.kind {
+                *found = Token::RBrace;
+            }
+            e
+        })
     }
 
     fn is_expression_start(&self) -> bool {
+        Self::starts_expression(self.peek())
+    }
+
+    fn starts_expression(token: &Token) -> bool {
         matches!(
-            self.peek(),
-            Token::Number(_) | Token::Floating(_) | Token::Boolean(_) | 
-            Token::String(_) | Token::Identifier(_) | Token::Lparen | 
-            Token::Minus | Token::Range
+            token,
+            Token::Number(_) | Token::Floating(_) | Token::Int32(_) | Token::UInt32(_) |
+            Token::Float32(_) | Token::Boolean(_) |
+            Token::String(_) | Token::InterpolatedString(_) | Token::Identifier(_) | Token::Lparen | Token::Lsquare | Token::LBrace |
+            Token::Minus | Token::Bang | Token::Range | Token::DotDot | Token::Match | Token::Fn
         )
     }
 
+    // Error for the token about to be consumed, which is not `expected`
+    fn expected(&self, expected: &str) -> ParseError {
+        ParseError {
+            kind: ParseErrorKind::Expected {
+                expected: expected.to_string(),
+                found: self.peek().clone(),
+            },
+            span: self.peek_span(),
+        }
+    }
+
+    fn consume(&mut self, token: &Token, expected: &str) -> ParseResult<()> {
+        if self.match_token(token) {
+            Ok(())
+        } else {
+            Err(self.expected(expected))
+        }
+    }
+
+    fn consume_identifier(&mut self, expected: &str) -> ParseResult<String> {
+        match self.peek() {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.expected(expected)),
+        }
+    }
+
     fn peek(&self) -> &Token {
-        self.tokens.get(self.current).unwrap_or(&Token::EOF)
+        self.tokens.get(self.current).map(|t| &t.token).unwrap_or(&Token::EOF)
     }
 
     fn peek_next(&self) -> Option<&Token> {
-        self.tokens.get(self.current + 1)
+        self.tokens.get(self.current + 1).map(|t| &t.token)
+    }
+
+    // Span of the token about to be consumed (the end of input if we ran out of tokens)
+    fn peek_span(&self) -> Span {
+        self.tokens
+            .get(self.current)
+            .or_else(|| self.tokens.last())
+            .map(|t| t.span)
+            .unwrap_or_default()
+    }
+
+    // Span of the most recently consumed token
+    fn previous_span(&self) -> Span {
+        self.current
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map(|t| t.span)
+            .unwrap_or_else(|| self.peek_span())
+    }
+
+    // Span from `start` up to the end of the most recently consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous_span())
     }
 
     fn advance(&mut self) -> Token {
@@ -554,7 +1392,11 @@ impl Parser {
     }
 
     fn previous(&self) -> Token {
-        self.tokens.get(self.current - 1).unwrap_or(&Token::EOF).clone()
+        self.current
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map(|t| t.token.clone())
+            .unwrap_or(Token::EOF)
     }
 
     fn is_at_end(&self) -> bool {
@@ -577,4 +1419,185 @@ impl Parser {
             std::mem::discriminant(self.peek()) == std::mem::discriminant(token)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn parse(source: &str) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        Parser::new(Lexer::new(source).tokenize().expect("source should lex")).parse()
+    }
+
+    // The expression of a one-line program, fully parenthesized
+    fn grouping(source: &str) -> String {
+        fn show(expr: &Expr) -> String {
+            let symbol = |op: &Token| op.to_string().trim_matches('`').to_string();
+            match &expr.kind {
+                ExprKind::Number(n) => n.to_string(),
+                ExprKind::Ident(name) => name.clone(),
+                ExprKind::Unary { op, expr } => format!("({}{})", symbol(op), show(expr)),
+                ExprKind::Binary { left, op, right } => format!("({} {} {})", show(left), symbol(op), show(right)),
+                ExprKind::Assignment { target, value } => format!("({} = {})", show(target), show(value)),
+                ExprKind::CompoundAssignment { target, op, value } => format!("({} {} {})", show(target), symbol(op), show(value)),
+                ExprKind::FunctionCall { callee, arguments } => {
+                    let arguments: Vec<String> = arguments.iter().map(show).collect();
+                    format!("{}({})", show(callee), arguments.join(", "))
+                }
+                other => panic!("unexpected expression {:?}", other),
+            }
+        }
+        match &parse(source).expect("source should parse")[..] {
+            [Stmt { kind: StmtKind::ExprStmt(expr), .. }] => show(expr),
+            other => panic!("expected one expression statement, got {:?}", other),
+        }
+    }
+
+    fn error_lines(source: &str) -> Vec<usize> {
+        parse(source).expect_err("source should not parse").iter().map(|e| e.span.line).collect()
+    }
+
+    #[test]
+    fn expressions_span_their_source() {
+        let program = parse("let total = 1 + 22\n").expect("source should parse");
+        let StmtKind::Let { value, .. } = &program[0].kind else {
+            panic!("expected a let");
+        };
+        assert_eq!((value.span.start, value.span.end, value.span.column), (12, 18, 13));
+        assert_eq!((program[0].span.start, program[0].span.end), (0, 18));
+    }
+
+    #[test]
+    fn parameters_carry_their_own_spans() {
+        let program = parse("fn add(a, b: Int):\n    return a\n").expect("source should parse");
+        let StmtKind::FunctionDef { params, .. } = &program[0].kind else {
+            panic!("expected a function definition");
+        };
+        let columns: Vec<usize> = params.iter().map(|param| param.span.column).collect();
+        assert_eq!(columns, vec![8, 11]);
+        assert_eq!(params[1].ty.as_deref(), Some("Int"));
+    }
+
+    #[test]
+    fn doc_comments_attach_to_the_declaration_below() {
+        let program = parse("/// Adds two numbers\n/// and returns the sum\nfn add(a, b):\n    return a + b\n").expect("source should parse");
+        let StmtKind::FunctionDef { doc, .. } = &program[0].kind else {
+            panic!("expected a function definition");
+        };
+        assert_eq!(doc.as_deref(), Some("Adds two numbers\nand returns the sum"));
+    }
+
+    #[test]
+    fn arithmetic_binds_tighter_than_comparison_and_logic() {
+        assert_eq!(grouping("1 + 2 * 3"), "(1 + (2 * 3))");
+        assert_eq!(grouping("a < b + 1 && c == d || e"), "(((a < (b + 1)) && (c == d)) || e)");
+        assert_eq!(grouping("a & b << 1 + c"), "(a & (b << (1 + c)))");
+        assert_eq!(grouping("f(x) * -y"), "(f(x) * (-y))");
+        assert_eq!(grouping("!a && b"), "((!a) && b)");
+    }
+
+    #[test]
+    fn operators_of_one_level_associate_left() {
+        assert_eq!(grouping("1 - 2 - 3"), "((1 - 2) - 3)");
+        assert_eq!(grouping("a / b * c % d"), "(((a / b) * c) % d)");
+    }
+
+    #[test]
+    fn power_associates_right_and_binds_looser_than_negation() {
+        assert_eq!(grouping("2 ** 3 ** 2"), "(2 ** (3 ** 2))");
+        assert_eq!(grouping("-2 ** 2"), "((-2) ** 2)");
+    }
+
+    #[test]
+    fn assignment_associates_right_and_needs_a_place() {
+        assert_eq!(grouping("a = b = 1"), "(a = (b = 1))");
+        assert_eq!(grouping("a -= b * 2"), "(a - (b * 2))");
+        let errors = parse("f() += 1\n").expect_err("a call is not assignable");
+        assert!(matches!(errors[0].kind, ParseErrorKind::InvalidAssignmentTarget));
+    }
+
+    #[test]
+    fn every_broken_statement_is_reported() {
+        assert_eq!(error_lines("let = 1\nlet ok = 2\nprint(ok +)\nlet x: = 3\n"), vec![1, 3, 4]);
+        assert_eq!(error_lines("fn f():\n    let = 1\n    return 2\nlet y = )\n"), vec![2, 4]);
+    }
+
+    #[test]
+    fn statements_after_an_unclosed_bracket_are_still_parsed() {
+        assert_eq!(error_lines("fn f(:\n    return 1\nlet y = )\n"), vec![1, 3]);
+        assert_eq!(error_lines("let a = [1, 2\nlet b = 3\nlet c = *\n"), vec![2, 3]);
+        assert_eq!(error_lines("print((1)\nlet f = fn(x) => x\nlet g = +\n"), vec![2, 3]);
+    }
+
+    #[test]
+    fn then_clauses_chain_into_nested_ifs() {
+        let source = "if a: x()\nthen b: y()\nthen c: z()\nelse: w()\n";
+        let mut stmt = &parse(source).expect("source should parse")[0];
+        let mut conditions = Vec::new();
+        while let StmtKind::IfStmt { condition, else_branch, .. } = &stmt.kind {
+            if let ExprKind::Ident(name) = &condition.kind {
+                conditions.push(name.clone());
+            }
+            match else_branch {
+                Some(next) => stmt = next,
+                None => break,
+            }
+        }
+        assert_eq!(conditions, ["a", "b", "c"]);
+        assert!(matches!(stmt.kind, StmtKind::Block(_)), "the chain should end in the else block");
+    }
+
+    #[test]
+    fn branch_arms_and_fallback_form_one_group() {
+        let source = "branch a => x()\nbranch b =>\n    y()\n    z()\nfallback => w()\n";
+        match &parse(source).expect("source should parse")[..] {
+            [Stmt { kind: StmtKind::Branch { arms, fallback: Some(_) }, .. }] => assert_eq!(arms.len(), 2),
+            other => panic!("expected one branch group, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn break_and_continue_need_a_loop_they_can_leave() {
+        let kinds = |source: &str| -> Vec<String> {
+            parse(source).expect_err("source should not parse").iter().map(|e| e.kind.to_string()).collect()
+        };
+        assert_eq!(kinds("break\n"), ["`break` outside of a loop"]);
+        assert_eq!(kinds("outer: while true:\n    for i in 0..3:\n        continue inner\n"), ["no enclosing loop is labelled `inner`"]);
+        assert_eq!(
+            kinds("while true:\n    parallel i in 0..4:\n        break\n"),
+            ["`break` cannot jump out of a `parallel` body; every lane runs to completion"]
+        );
+        assert!(parse("parallel i in 0..4:\n    continue\n").is_ok(), "`continue` only ends the lane");
+    }
+
+    #[test]
+    fn step_is_a_keyword_only_before_an_expression() {
+        let range = |source: &str| match &parse(source).expect("source should parse")[..] {
+            [Stmt { kind: StmtKind::ExprStmt(Expr { kind: ExprKind::Range { end, step, .. }, .. }), .. }] => {
+                (end.as_ref().map(|end| end.kind.clone()), step.is_some())
+            }
+            other => panic!("expected one range, got {:?}", other),
+        };
+        assert_eq!(range("0..step\n"), (Some(ExprKind::Ident("step".to_string())), false));
+        assert_eq!(range("0..n step 2\n"), (Some(ExprKind::Ident("n".to_string())), true));
+        assert_eq!(range("0.. step 2\n"), (None, true));
+        assert!(matches!(range("0..step - 1\n"), (Some(ExprKind::Binary { .. }), false)));
+        assert!(parse("let step = 2\nfor i in 0..step:\n    print(a[..step])\n").is_ok());
+    }
+
+    #[test]
+    fn errors_on_the_last_line_of_a_block_are_recovered_from() {
+        assert_eq!(error_lines("struct P:\n    x: Int\n    x: Int\nlet y = )\n"), vec![3, 4]);
+        assert_eq!(error_lines("fn f():\n    if true:\n        let = 1\nlet y = )\n"), vec![3, 4]);
+    }
+
+    #[test]
+    fn fields_are_given_once() {
+        let kinds = |source: &str| -> Vec<String> {
+            parse(source).expect_err("source should not parse").iter().map(|e| e.kind.to_string()).collect()
+        };
+        assert_eq!(kinds("struct P:\n    x: Int\n    x: Int\n"), ["field `x` is given more than once"]);
+        assert_eq!(kinds("let p = P { x: 1, x: 2 }\n"), ["field `x` is given more than once"]);
+    }
+}
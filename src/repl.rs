@@ -1,10 +1,12 @@
 // || shree ganesh ||
 // REPL (Read-Eval-Print Loop) for Vortex Language
 
+use crate::ast::{Stmt, StmtKind};
+use crate::checker::Checker;
 use crate::interpreter::Interpreter;
 use crate::lexer::Lexer;
 use crate::parser::Parser;
-use crate::token::Token;
+use crate::typeck::TypeChecker;
 use colored::*;
 use rustyline::error::ReadlineError;
 use rustyline::{DefaultEditor, Result};
@@ -13,11 +15,12 @@ use std::path::Path;
 
 pub struct VortexRepl {
     interpreter: Interpreter,
+    checker: Checker, // remembers declarations from earlier input
+    type_checker: TypeChecker,
     editor: DefaultEditor,
     version: String,
     multi_line_buffer: String,
     in_multi_line: bool,
-    bracket_depth: i32,
 }
 
 impl VortexRepl {
@@ -29,11 +32,12 @@ impl VortexRepl {
         
         Ok(VortexRepl {
             interpreter: Interpreter::new(),
+            checker: Checker::new(),
+            type_checker: TypeChecker::new(),
             editor,
             version: "0.1.0".to_string(),
             multi_line_buffer: String::new(),
             in_multi_line: false,
-            bracket_depth: 0,
         })
     }
 
@@ -112,10 +116,13 @@ impl VortexRepl {
         println!("  {}  - Show command history", ":history".cyan());
         println!("  {}  - Load and execute a Vortex file", ":load <file>".cyan());
         println!("  {}  - Show current environment variables", ":env".cyan());
+        println!("  {}  - Show the documentation of a declaration (`Type.method` for methods)", ":doc <name>".cyan());
+        println!("  {}  - Show the inferred type of an expression without running it", ":type <expr>".cyan());
         println!("  {}  - Reset the interpreter state", ":reset".cyan());
         println!();
         println!("{}", "Multi-line input:".yellow().bold());
         println!("  - Use {} or {} to start multi-line blocks", "if:".cyan(), "fn:".cyan());
+        println!("  - Indent the block body and enter an empty line to finish it");
         println!("  - Press {} to cancel multi-line input", "Ctrl+C".cyan());
         println!();
         println!("{}", "Enter Vortex code or use :help for commands".green());
@@ -131,7 +138,7 @@ impl VortexRepl {
     }
 
     fn handle_command(&mut self, command: &str) -> bool {
-        let parts: Vec<&str> = command.trim().split_whitespace().collect();
+        let parts: Vec<&str> = command.split_whitespace().collect();
         if parts.is_empty() {
             return false;
         }
@@ -161,8 +168,28 @@ impl VortexRepl {
             ":env" => {
                 self.show_environment();
             }
+            ":doc" => {
+                if parts.len() < 2 {
+                    println!("{} Usage: :doc <name>", "Error:".red());
+                } else {
+                    match self.interpreter.doc(parts[1]) {
+                        Some(doc) => println!("{}", doc),
+                        None => println!("{} No documentation for '{}'", "Info:".blue(), parts[1]),
+                    }
+                }
+            }
+            ":type" | ":t" => {
+                let expression = command.trim().split_once(char::is_whitespace).map_or("", |(_, rest)| rest);
+                if expression.trim().is_empty() {
+                    println!("{} Usage: :type <expr>", "Error:".red());
+                } else {
+                    self.show_type(expression);
+                }
+            }
             ":reset" => {
                 self.interpreter = Interpreter::new();
+                self.checker = Checker::new();
+                self.type_checker = TypeChecker::new();
                 println!("{}", "Interpreter state reset.".green());
             }
             _ => {
@@ -174,128 +201,158 @@ impl VortexRepl {
     }
 
     fn handle_input(&mut self, input: &str) {
-        let line = input.trim();
-        
         // Check if this starts or continues a multi-line block
-        if self.should_start_multi_line(line) || self.in_multi_line {
-            self.handle_multi_line_input(line);
+        if self.in_multi_line || self.should_start_multi_line(input) {
+            self.handle_multi_line_input(input);
             return;
         }
 
         // Single line input
-        self.execute_code(line);
+        self.execute_code(input.trim(), "<repl>");
     }
 
+    // Any line ending in ':' opens an indented block (if/then/else, loops, functions).
+    // `///` doc comments also start one, so they stay attached to the declaration below.
     fn should_start_multi_line(&self, line: &str) -> bool {
         let trimmed = line.trim();
-        trimmed.ends_with(':') && (
-            trimmed.starts_with("if ") ||
-            trimmed.starts_with("then ") ||
-            trimmed.starts_with("else:") ||
-            trimmed.starts_with("for ") ||
-            trimmed.starts_with("parallel ") ||
-            trimmed.starts_with("fn ") ||
-            trimmed.starts_with("@gpu fn ") ||
-            trimmed.starts_with("branch ") ||
-            trimmed.starts_with("fallback")
-        )
+        trimmed.ends_with(':') || trimmed.starts_with("///")
     }
 
     fn handle_multi_line_input(&mut self, line: &str) {
         if !self.in_multi_line {
             self.in_multi_line = true;
             self.multi_line_buffer.clear();
-            self.bracket_depth = 0;
-        }
-
-        // Add the line to buffer
-        if !self.multi_line_buffer.is_empty() {
-            self.multi_line_buffer.push('\n');
+            // The block header sets the base indentation level
+            self.multi_line_buffer.push_str(line.trim_start());
+            return;
         }
-        self.multi_line_buffer.push_str(line);
 
-        // Simple heuristic: if line is empty or doesn't start with whitespace, 
-        // and we're not in a nested block, consider the multi-line input complete
-        if line.trim().is_empty() || 
-           (!line.starts_with(' ') && !line.starts_with('\t') && self.bracket_depth <= 0) {
-            
-            // Check if we have a complete block
-            if self.is_complete_block() {
-                let code = self.multi_line_buffer.clone();
-                self.reset_multi_line();
-                self.execute_code(&code);
-            }
+        // An empty line finishes the block, as in Python's REPL
+        if line.trim().is_empty() {
+            let code = self.multi_line_buffer.clone();
+            self.reset_multi_line();
+            self.execute_code(&code, "<repl>");
+            return;
         }
-    }
 
-    fn is_complete_block(&self) -> bool {
-        // Simple check: if buffer contains keywords that typically end blocks
-        let buffer = self.multi_line_buffer.trim();
-        
-        // Check for function definitions
-        if buffer.contains("fn ") && buffer.contains("return") {
-            return true;
-        }
-        
-        // Check for control structures with bodies
-        if (buffer.contains("if ") || buffer.contains("for ") || buffer.contains("parallel ")) 
-           && !buffer.trim().ends_with(':') {
-            return true;
-        }
-        
-        // Check for single-line statements that end with colon but have content
-        if buffer.lines().count() > 1 {
-            let last_line = buffer.lines().last().unwrap_or("").trim();
-            return !last_line.is_empty() && !last_line.ends_with(':');
-        }
-        
-        false
+        // Keep the indentation: it is what delimits the block
+        self.multi_line_buffer.push('\n');
+        self.multi_line_buffer.push_str(line);
     }
 
     fn reset_multi_line(&mut self) {
         self.in_multi_line = false;
         self.multi_line_buffer.clear();
-        self.bracket_depth = 0;
     }
 
-    fn execute_code(&mut self, code: &str) {
+    // `source_name` is what error locations are reported against, e.g. `<repl>:1:9`
+    fn execute_code(&mut self, code: &str, source_name: &str) {
         if code.trim().is_empty() {
             return;
         }
 
         // Tokenize
         let mut lexer = Lexer::new(code);
-        let mut tokens = Vec::new();
-
-        loop {
-            let token = lexer.next_token();
-            if token == Token::EOF {
-                break;
+        let tokens = match lexer.tokenize() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                for e in errors {
+                    println!("{} {}:{}: {}", "Lexical Error:".red(), source_name, e.span, e.kind);
+                }
+                return;
             }
-            tokens.push(token);
-        }
+        };
 
-        if tokens.is_empty() {
-            return;
+        if tokens.len() <= 1 {
+            return; // only EOF
         }
 
         // Parse
         let mut parser = Parser::new(tokens);
-        let statements = parser.parse();
+        let statements = match parser.parse() {
+            Ok(statements) => statements,
+            Err(errors) => {
+                for e in errors {
+                    println!("{} {}:{}: {}", "Syntax Error:".red(), source_name, e.span, e.kind);
+                }
+                return;
+            }
+        };
 
         if statements.is_empty() {
             println!("{} No valid statements found.", "Warning:".yellow());
             return;
         }
 
+        let errors = self.checker.check(&statements);
+        if !errors.is_empty() {
+            for e in errors {
+                println!("{} {}:{}: {}", "Error:".red(), source_name, e.span, e.message);
+            }
+            return;
+        }
+
+        let errors = self.type_checker.check(&statements);
+        if !errors.is_empty() {
+            for e in errors {
+                println!("{} {}:{}: {}", "Type Error:".red(), source_name, e.span, e.message);
+            }
+            return;
+        }
+
         // Execute each statement
+        let mut succeeded = true;
         for stmt in statements {
             match self.interpreter.interpret(vec![stmt]) {
                 Ok(_) => {
                     // Success - no output needed unless it's an expression
                 }
                 Err(e) => {
-                    println!("{} {}", "Runtime Error:".red(), e);
+                    succeeded = false;
+                    match e.span {
+                        Some(span) => println!("{} {}:{}: {}", "Runtime Error:".red(), source_name, span, e.message),
+                        None => println!("{} {}", "Runtime Error:".red(), e.message),
+                    }
+                }
+            }
+        }
+
+        // The inferred types of the variables and functions just defined
+        if succeeded {
+            for binding in self.type_checker.bindings().iter().filter(|binding| binding.top_level) {
+                println!("{}", format!("{}: {}", binding.name, binding.ty).dimmed());
+            }
+        }
+    }
+
+    fn show_type(&mut self, code: &str) {
+        let tokens = match Lexer::new(code).tokenize() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                for e in errors {
+                    println!("{} {}: {}", "Lexical Error:".red(), e.span, e.kind);
+                }
+                return;
+            }
+        };
+        let statements = match Parser::new(tokens).parse() {
+            Ok(statements) => statements,
+            Err(errors) => {
+                for e in errors {
+                    println!("{} {}: {}", "Syntax Error:".red(), e.span, e.kind);
+                }
+                return;
+            }
+        };
+        let [Stmt { kind: StmtKind::ExprStmt(expr), .. }] = statements.as_slice() else {
+            println!("{} :type takes a single expression", "Error:".red());
+            return;
+        };
+        match self.type_checker.type_of(expr) {
+            Ok(ty) => println!("{}", ty.to_string().cyan()),
+            Err(errors) => {
+                for e in errors {
+                    println!("{} {}: {}", "Type Error:".red(), e.span, e.message);
                 }
             }
         }
@@ -314,7 +371,7 @@ impl VortexRepl {
         match fs::read_to_string(path) {
             Ok(content) => {
                 println!("{} Loading file: {}", "Info:".blue(), filename.cyan());
-                self.execute_code(&content);
+                self.execute_code(&content, filename);
                 println!("{} File executed successfully.", "Success:".green());
             }
             Err(e) => {
@@ -3,7 +3,10 @@
 // why a separate file for tokens? well cuz we want to keep the code organized and maintainable. And avoid circular dependencies!!.
 //
 
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
     //Keywords
     Let,
@@ -12,10 +15,17 @@ pub enum Token {
     Then,
     Else,
     For,
+    While,
+    Break,
+    Continue,
     In,
     Range,
     Fn,
     Return,
+    Struct,
+    Impl,
+    Enum,
+    Match,
 
     //VORTEX mode
     Branch,
@@ -28,7 +38,9 @@ pub enum Token {
     Plus,     // +
     Minus,    // -
     Star,     // *
+    StarStar, // **
     Slash,    // /
+    Percent,  // %
     Lparen,   // (
     Rparen,   // )
     LBrace,   // {
@@ -36,6 +48,7 @@ pub enum Token {
     Lsquare,  // [
     Rsquare,  // ]
     Dot,      // .
+    DotDot,   // ..
     Comma,    // ,
     Colon,    // :
     Arrow,    // ->
@@ -49,13 +62,204 @@ pub enum Token {
     EQ, // ==
     NE, // !=
 
+    //Logical and bitwise
+    AndAnd, // &&
+    OrOr,   // ||
+    Bang,   // !
+    Amp,    // &
+    Pipe,   // |
+    Caret,  // ^
+    Shl,    // <<
+    Shr,    // >>
+
+    //Compound assignment
+    PlusEq,     // +=
+    MinusEq,    // -=
+    StarEq,     // *=
+    StarStarEq, // **=
+    SlashEq,    // /=
+    PercentEq,  // %=
+    AmpEq,      // &=
+    PipeEq,     // |=
+    CaretEq,    // ^=
+    ShlEq,      // <<=
+    ShrEq,      // >>=
+
     // Literals
-    Number(i64),
-    Floating(f64),
+    Number(i64),   // 64-bit integer, also written with an `i64` suffix
+    Floating(f64), // 64-bit float, also written with an `f64` suffix
+    Int32(i32),    // 42i32
+    UInt32(u32),   // 42u32
+    Float32(f32),  // 1.0f32
     String(String),
+    InterpolatedString(Vec<StringPart>), // "a ${b} c"
     Boolean(bool),
     Identifier(String),
 
+    // `/// text` -- kept so the parser can attach it to the next declaration
+    DocComment(String),
+
+    //Layout: emitted by the lexer from line breaks and indentation
+    Newline, // end of a logical line
+    Indent,  // line is indented deeper than the enclosing block
+    Dedent,  // line closes one level of indentation
+
     //Other
     EOF,
 }
+
+impl Token {
+    // The binary operator a compound assignment applies, e.g. `+=` -> `+`
+    pub fn compound_op(&self) -> Option<Token> {
+        match self {
+            Token::PlusEq => Some(Token::Plus),
+            Token::MinusEq => Some(Token::Minus),
+            Token::StarEq => Some(Token::Star),
+            Token::StarStarEq => Some(Token::StarStar),
+            Token::SlashEq => Some(Token::Slash),
+            Token::PercentEq => Some(Token::Percent),
+            Token::AmpEq => Some(Token::Amp),
+            Token::PipeEq => Some(Token::Pipe),
+            Token::CaretEq => Some(Token::Caret),
+            Token::ShlEq => Some(Token::Shl),
+            Token::ShrEq => Some(Token::Shr),
+            _ => None,
+        }
+    }
+}
+
+// How a token is named in diagnostics, e.g. "expected `:`, found end of line"
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Token::Let => "let",
+            Token::Mut => "mut",
+            Token::If => "if",
+            Token::Then => "then",
+            Token::Else => "else",
+            Token::For => "for",
+            Token::While => "while",
+            Token::Break => "break",
+            Token::Continue => "continue",
+            Token::In => "in",
+            Token::Range => "range",
+            Token::Fn => "fn",
+            Token::Return => "return",
+            Token::Struct => "struct",
+            Token::Impl => "impl",
+            Token::Enum => "enum",
+            Token::Match => "match",
+            Token::Branch => "branch",
+            Token::Fallback => "fallback",
+            Token::Parallel => "parallel",
+            Token::GPU => "@gpu",
+            Token::Equals => "=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::StarStar => "**",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Lparen => "(",
+            Token::Rparen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::Lsquare => "[",
+            Token::Rsquare => "]",
+            Token::Dot => ".",
+            Token::DotDot => "..",
+            Token::Comma => ",",
+            Token::Colon => ":",
+            Token::Arrow => "->",
+            Token::FatArrow => "=>",
+            Token::GT => ">",
+            Token::LT => "<",
+            Token::GE => ">=",
+            Token::LE => "<=",
+            Token::EQ => "==",
+            Token::NE => "!=",
+            Token::AndAnd => "&&",
+            Token::OrOr => "||",
+            Token::Bang => "!",
+            Token::Amp => "&",
+            Token::Pipe => "|",
+            Token::Caret => "^",
+            Token::Shl => "<<",
+            Token::Shr => ">>",
+            Token::PlusEq => "+=",
+            Token::MinusEq => "-=",
+            Token::StarEq => "*=",
+            Token::StarStarEq => "**=",
+            Token::SlashEq => "/=",
+            Token::PercentEq => "%=",
+            Token::AmpEq => "&=",
+            Token::PipeEq => "|=",
+            Token::CaretEq => "^=",
+            Token::ShlEq => "<<=",
+            Token::ShrEq => ">>=",
+            Token::Number(n) => return write!(f, "number `{}`", n),
+            Token::Floating(n) => return write!(f, "number `{}`", n),
+            Token::Int32(n) => return write!(f, "number `{}i32`", n),
+            Token::UInt32(n) => return write!(f, "number `{}u32`", n),
+            Token::Float32(n) => return write!(f, "number `{}f32`", n),
+            Token::String(_) | Token::InterpolatedString(_) => return write!(f, "string literal"),
+            Token::Boolean(b) => return write!(f, "`{}`", b),
+            Token::Identifier(name) => return write!(f, "identifier `{}`", name),
+            Token::DocComment(_) => return write!(f, "doc comment"),
+            Token::Newline => return write!(f, "end of line"),
+            Token::Indent => return write!(f, "indentation"),
+            Token::Dedent => return write!(f, "end of block"),
+            Token::EOF => return write!(f, "end of input"),
+        };
+        write!(f, "`{}`", symbol)
+    }
+}
+
+// A piece of an interpolated string literal: either literal text (escapes already
+// resolved) or the tokens of an embedded `${...}` expression, terminated by an EOF token
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Literal(String),
+    Code(Vec<SpannedToken>),
+}
+
+// Where a token (or an AST node built from tokens) sits in the source.
+// line and column are 1-based and counted in characters, start/end are byte offsets
+// so tools can slice the original source directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Span { start, end, line, column }
+    }
+
+    // Span covering both self and other, keeping the line/column of whichever comes first
+    pub fn to(self, other: Span) -> Span {
+        let first = if self.start <= other.start { self } else { other };
+        Span {
+            start: first.start,
+            end: self.end.max(other.end),
+            line: first.line,
+            column: first.column,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+// A token together with the place it was found
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
@@ -0,0 +1,1425 @@
+// || shree ganesh ||
+// Type checking with local inference, in the style of Hindley-Milner. Annotations (`let x:
+// T`, parameter and return types, field types) are checked as written. An unannotated `let`
+// takes the type of its value, and unannotated parameters and results start as type
+// variables that are bound by how the function uses them: `fn twice(f, x): return f(f(x))`
+// is `fn(fn(a) -> a, a) -> a`. Variables still unbound after a function is checked make it
+// generic, so each call binds them afresh. `Any` fits everywhere and is what inference
+// falls back to where the runtime rules are too loose to pin a type down.
+
+use crate::ast::{Expr, ExprKind, Param, Pattern, PatternKind, Stmt, StmtKind, StringSegment};
+use crate::checker::CheckError;
+use crate::numeric::NumericType;
+use crate::token::{Span, Token};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Any, // not known before running the program
+    Number(NumericType),
+    String,
+    Bool,
+    Nil,
+    Array(Box<Type>), // arrays and slices
+    Map(Box<Type>, Box<Type>),
+    Set(Box<Type>),
+    Tuple(Vec<Type>),
+    // Parameter types (None: any number of arguments) and result type
+    Function(Option<Vec<Type>>, Box<Type>),
+    Struct(String),
+    Enum(String),
+    Var(u32),       // a type inference has not determined yet
+    NumberVar(u32), // a variable arithmetic has shown to be some number
+}
+
+impl Type {
+    fn int() -> Type {
+        Type::Number(NumericType::Int)
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(self, Type::Number(n) if !n.is_float())
+    }
+
+    // The name `impl` blocks for values of this type are registered under, as the
+    // interpreter's `method_type` computes it
+    fn impl_name(&self) -> Option<String> {
+        Some(match self {
+            Type::Any => return None,
+            Type::Number(n) => n.name().to_string(),
+            Type::String => "String".to_string(),
+            Type::Bool => "Boolean".to_string(),
+            Type::Nil => "Nil".to_string(),
+            Type::Array(_) => "Array".to_string(),
+            Type::Map(..) => "Map".to_string(),
+            Type::Set(_) => "Set".to_string(),
+            Type::Tuple(_) => "Tuple".to_string(),
+            Type::Function(..) => "Function".to_string(),
+            Type::Struct(name) | Type::Enum(name) => name.clone(),
+            Type::Var(_) | Type::NumberVar(_) => return None,
+        })
+    }
+
+    fn contains_var(&self, var: u32) -> bool {
+        let mut found = false;
+        self.visit_vars(&mut |v| found |= v == var);
+        found
+    }
+
+    fn visit_vars(&self, visit: &mut impl FnMut(u32)) {
+        match self {
+            Type::Var(v) | Type::NumberVar(v) => visit(*v),
+            Type::Array(inner) | Type::Set(inner) => inner.visit_vars(visit),
+            Type::Map(key, value) => {
+                key.visit_vars(visit);
+                value.visit_vars(visit);
+            }
+            Type::Tuple(types) => types.iter().for_each(|ty| ty.visit_vars(visit)),
+            Type::Function(params, result) => {
+                params.iter().flatten().for_each(|ty| ty.visit_vars(visit));
+                result.visit_vars(visit);
+            }
+            _ => {}
+        }
+    }
+
+    // The type with each variable replaced by `replace(variable, numeric)`, where that is
+    // not None
+    fn map_vars(&self, replace: &mut impl FnMut(u32, bool) -> Option<Type>) -> Type {
+        let mut map = |ty: &Type| Box::new(ty.map_vars(replace));
+        match self {
+            Type::Var(v) => replace(*v, false).unwrap_or(Type::Var(*v)),
+            Type::NumberVar(v) => replace(*v, true).unwrap_or(Type::NumberVar(*v)),
+            Type::Array(inner) => Type::Array(map(inner)),
+            Type::Set(inner) => Type::Set(map(inner)),
+            Type::Map(key, value) => Type::Map(map(key), map(value)),
+            Type::Tuple(types) => Type::Tuple(types.iter().map(|ty| ty.map_vars(replace)).collect()),
+            Type::Function(params, result) => Type::Function(
+                params.as_ref().map(|params| params.iter().map(|ty| ty.map_vars(replace)).collect()),
+                Box::new(result.map_vars(replace)),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn is_var(&self) -> bool {
+        matches!(self, Type::Var(_) | Type::NumberVar(_))
+    }
+
+    // The same type with its variables renamed `a`, `b`, ... in order of appearance, as
+    // inferred types are shown. Numeric variables all show as `Number`.
+    pub fn normalized(&self) -> Type {
+        self.normalized_with(&mut HashMap::new())
+    }
+
+    // Normalizes with names shared with other types, so that the same variable has the
+    // same name in each
+    fn normalized_with(&self, names: &mut HashMap<u32, u32>) -> Type {
+        self.map_vars(&mut |v, numeric| {
+            let next = names.len() as u32;
+            (!numeric).then(|| Type::Var(*names.entry(v).or_insert(next)))
+        })
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |types: &[Type]| types.iter().map(Type::to_string).collect::<Vec<_>>().join(", ");
+        match self {
+            Type::Any => write!(f, "Any"),
+            Type::Number(n) => write!(f, "{}", n.name()),
+            Type::String => write!(f, "String"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Nil => write!(f, "Nil"),
+            Type::Array(element) if **element == Type::Any => write!(f, "Array"),
+            Type::Array(element) => write!(f, "Array<{}>", element),
+            Type::Map(key, value) if **key == Type::Any && **value == Type::Any => write!(f, "Map"),
+            Type::Map(key, value) => write!(f, "Map<{}, {}>", key, value),
+            Type::Set(element) if **element == Type::Any => write!(f, "Set"),
+            Type::Set(element) => write!(f, "Set<{}>", element),
+            Type::Tuple(elements) if elements.len() == 1 => write!(f, "({},)", elements[0]),
+            Type::Tuple(elements) => write!(f, "({})", list(elements)),
+            Type::Function(None, result) if **result == Type::Any => write!(f, "Function"),
+            Type::Function(None, result) => write!(f, "fn(..) -> {}", result),
+            Type::Function(Some(params), result) => write!(f, "fn({}) -> {}", list(params), result),
+            Type::Struct(name) | Type::Enum(name) => write!(f, "{}", name),
+            Type::Var(v) if *v < 26 => write!(f, "{}", (b'a' + *v as u8) as char),
+            Type::Var(v) => write!(f, "t{}", v),
+            Type::NumberVar(_) => write!(f, "Number"),
+        }
+    }
+}
+
+// Result types of the built-in methods in `builtins::call_method`, by receiver, method
+// and argument count. None if the receiver has no such method.
+fn builtin_method(receiver: &Type, method: &str, arity: usize) -> Option<Type> {
+    let strings = || Type::Array(Box::new(Type::String));
+    Some(match (receiver, method, arity) {
+        (Type::String, "length", 0) => Type::int(),
+        (Type::String, "upper" | "lower" | "trim", 0) | (Type::String, "replace", 2) => Type::String,
+        (Type::String, "starts_with" | "ends_with" | "contains", 1) => Type::Bool,
+        (Type::String, "split", 1) | (Type::String, "chars", 0) => strings(),
+        (Type::Array(_), "push", 1) | (Type::Array(_), "reverse" | "sort", 0) => Type::Nil,
+        (Type::Array(element), "pop", 0) => (**element).clone(),
+        (Type::Array(_), "length", 0) | (Type::Array(_), "index_of", 1) => Type::int(),
+        (Type::Array(_), "join", 1) => Type::String,
+        (Type::Array(_), "contains", 1) => Type::Bool,
+        (Type::Map(..) | Type::Set(_), "length", 0) => Type::int(),
+        (Type::Map(_, value), "get", 2) => (**value).clone(),
+        (Type::Map(key, _), "keys", 0) => Type::Array(key.clone()),
+        (Type::Map(_, value), "values", 0) => Type::Array(value.clone()),
+        (Type::Map(..), "remove" | "contains", 1) | (Type::Set(_), "insert" | "remove" | "contains", 1) => Type::Bool,
+        _ => return None,
+    })
+}
+
+// Types of the functions in `builtins` that have a fixed result type
+fn builtin_function(name: &str) -> Option<Type> {
+    let any_params = |count: usize, result: Type| Type::Function(Some(vec![Type::Any; count]), Box::new(result));
+    if let Some(numeric_type) = NumericType::from_suffix(name) {
+        return Some(any_params(1, Type::Number(numeric_type)));
+    }
+    match name {
+        "print" | "set" => Some(Type::Function(None, Box::new(Type::Any))),
+        "keys" | "values" => Some(any_params(1, Type::Array(Box::new(Type::Any)))),
+        "enumerate" => Some(any_params(1, Type::Array(Box::new(Type::Tuple(vec![Type::int(), Type::Any]))))),
+        "contains" | "insert" | "remove" => Some(any_params(2, Type::Bool)),
+        _ => None,
+    }
+}
+
+// Whether running `stmt` always ends in a `return`, so that control never falls off the
+// end of a function body after it. Loops may run zero times and never count.
+fn always_returns(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::Return(_) => true,
+        StmtKind::Block(stmts) => stmts.iter().any(always_returns),
+        StmtKind::IfStmt { then_branch, else_branch: Some(else_branch), .. } => {
+            always_returns(then_branch) && always_returns(else_branch)
+        }
+        StmtKind::Branch { arms, fallback: Some(fallback) } => {
+            arms.iter().all(|(_, body)| always_returns(body)) && always_returns(fallback)
+        }
+        StmtKind::ExprStmt(Expr { kind: ExprKind::Match { arms, .. }, .. }) => {
+            arms.iter().all(|arm| always_returns(&arm.body))
+        }
+        _ => false,
+    }
+}
+
+// A name the program binds and the type inferred for it, for the REPL and for
+// `vortex-lang --types`
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: String,
+    pub ty: Type,
+    pub span: Span,
+    pub top_level: bool,
+}
+
+// The function whose body is being checked
+#[derive(Clone)]
+struct Returning {
+    function: String,
+    result: Type,
+    inferred: bool, // no `-> T`: `return` statements determine the result type
+    seen: bool,
+}
+
+// Keeps declarations and top-level variables between calls, so the REPL can check one
+// line at a time
+#[derive(Clone)]
+pub struct TypeChecker {
+    structs: HashMap<String, Vec<(String, Type)>>,
+    enums: HashMap<String, Vec<(String, Vec<Type>)>>,
+    methods: HashMap<String, HashMap<String, Type>>, // by type, without the `self` parameter
+    scopes: Vec<HashMap<String, (Type, bool)>>, // with whether each name is `let mut`
+    parallel: Vec<usize>,                       // the scopes of the `parallel` bodies being checked
+    returns: Vec<Returning>, // innermost last
+    // What each type variable is bound to, by number
+    vars: Vec<Option<Type>>,
+    // Variables of generic functions, replaced by fresh ones wherever the function is used
+    generic: HashSet<u32>,
+    // Unbound variables that can only be bound to numbers
+    numeric: HashSet<u32>,
+    bindings: Vec<Binding>,
+    errors: Vec<CheckError>,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        TypeChecker {
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            methods: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            parallel: Vec::new(),
+            returns: Vec::new(),
+            vars: Vec::new(),
+            generic: HashSet::new(),
+            numeric: HashSet::new(),
+            bindings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    // All type errors in the program. A program with errors leaves no declarations or
+    // variables behind.
+    pub fn check(&mut self, program: &[Stmt]) -> Vec<CheckError> {
+        let before = self.clone();
+        self.bindings.clear();
+        // Struct and enum names first, so field types may name types declared later
+        for stmt in program {
+            self.declare_names(stmt);
+        }
+        for stmt in program {
+            self.declare(stmt);
+        }
+        self.declare_functions(program);
+        for stmt in program {
+            self.stmt(stmt);
+        }
+        let errors = std::mem::take(&mut self.errors);
+        if !errors.is_empty() {
+            *self = before;
+        }
+        errors
+    }
+
+    // The names the last checked program bound, with their types as far as they were
+    // inferred
+    pub fn bindings(&self) -> Vec<Binding> {
+        // A top-level binding names its variables together with the bindings inside it,
+        // such as a function's parameters
+        let mut names = HashMap::new();
+        let mut resolve = |binding: &Binding| {
+            if binding.top_level {
+                names.clear();
+            }
+            Binding { ty: self.resolve(&binding.ty).normalized_with(&mut names), ..binding.clone() }
+        };
+        self.bindings.iter().map(&mut resolve).collect()
+    }
+
+    // The type of an expression in the current scope, for the REPL's `:type`. Nothing it
+    // binds is kept.
+    pub fn type_of(&mut self, expr: &Expr) -> Result<Type, Vec<CheckError>> {
+        let before = self.clone();
+        let ty = self.expr(expr).normalized();
+        let errors = std::mem::take(&mut self.errors);
+        *self = before;
+        if errors.is_empty() {
+            Ok(ty)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn error(&mut self, span: Span, message: String) {
+        self.errors.push(CheckError { message, span });
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.vars.push(None);
+        Type::Var(self.vars.len() as u32 - 1)
+    }
+
+    // The type with every bound variable replaced by what it is bound to, and every
+    // numeric one by a NumberVar
+    fn resolve(&self, ty: &Type) -> Type {
+        ty.map_vars(&mut |v, _| match &self.vars[v as usize] {
+            Some(bound) => Some(self.resolve(bound)),
+            None if self.numeric.contains(&v) => Some(Type::NumberVar(v)),
+            None => Some(Type::Var(v)),
+        })
+    }
+
+    // Restricts the variables among `types` to numbers; false if one is already bound to
+    // something else
+    fn numeric(&mut self, types: &[&Type]) -> bool {
+        types.iter().all(|ty| match self.resolve(ty) {
+            Type::Var(v) if !self.generic.contains(&v) => {
+                self.numeric.insert(v);
+                true
+            }
+            Type::Var(_) | Type::NumberVar(_) | Type::Number(_) | Type::Any => true,
+            _ => false,
+        })
+    }
+
+    // The variable at the end of a chain of variables bound to each other
+    fn root(&self, mut var: u32) -> u32 {
+        while let Some(Type::Var(next) | Type::NumberVar(next)) = &self.vars[var as usize] {
+            var = *next;
+        }
+        var
+    }
+
+    // Whether a value of type `found` may be stored where `expected` is declared, binding
+    // the unbound variables of either so that it can. Integers widen to floats, as
+    // annotations convert them at run time, but floats never narrow.
+    fn fits(&mut self, expected: &Type, found: &Type) -> bool {
+        match (self.resolve(expected), self.resolve(found)) {
+            (Type::Var(e) | Type::NumberVar(e), Type::Var(f) | Type::NumberVar(f)) => {
+                // The newer variable is bound to the older one, so that a variable a
+                // function shares with its surroundings is never made generic
+                let (newer, older) = if e > f { (e, f) } else { (f, e) };
+                if newer != older {
+                    if self.numeric.contains(&newer) && !self.numeric(&[&Type::Var(older)]) {
+                        return false;
+                    }
+                    self.bind(newer, Type::Var(older));
+                }
+                true
+            }
+            (Type::NumberVar(_), ty) | (ty, Type::NumberVar(_)) if !matches!(ty, Type::Number(_) | Type::Any) => false,
+            (Type::Var(v) | Type::NumberVar(v), ty) | (ty, Type::Var(v) | Type::NumberVar(v)) => {
+                self.bind(v, ty);
+                true
+            }
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Number(e), Type::Number(f)) => e.is_float() || !f.is_float(),
+            (Type::Array(e), Type::Array(f)) | (Type::Set(e), Type::Set(f)) => self.fits(&e, &f),
+            (Type::Map(ek, ev), Type::Map(fk, fv)) => self.fits(&ek, &fk) && self.fits(&ev, &fv),
+            (Type::Tuple(e), Type::Tuple(f)) => e.len() == f.len() && e.iter().zip(&f).all(|(e, f)| self.fits(e, f)),
+            (Type::Function(ep, er), Type::Function(fp, fr)) => {
+                let params_fit = match (ep, fp) {
+                    (Some(ep), Some(fp)) => ep.len() == fp.len() && ep.iter().zip(&fp).all(|(e, f)| self.fits(f, e)),
+                    _ => true,
+                };
+                params_fit && self.fits(&er, &fr)
+            }
+            (e, f) => e == f,
+        }
+    }
+
+    // A type containing the variable itself cannot be bound to it; such uses stay unchecked.
+    // Variables of a generic function stand for any type and are never bound.
+    fn bind(&mut self, var: u32, ty: Type) {
+        if !ty.contains_var(var) && !self.generic.contains(&var) {
+            self.vars[var as usize] = Some(ty);
+        }
+    }
+
+    // The type of a collection holding values of both types
+    fn join(&mut self, a: Type, b: &Type) -> Type {
+        let (a, b) = (self.resolve(&a), self.resolve(b));
+        if a == b || (a.is_var() || b.is_var()) && self.fits(&a, &b) {
+            self.resolve(&a)
+        } else {
+            Type::Any
+        }
+    }
+
+    // Makes the variables of `ty` numbered from `first` on generic, once nothing outside
+    // the function they belong to can bind them any more
+    fn generalize(&mut self, ty: &Type, first: u32) {
+        let mut generic = Vec::new();
+        self.resolve(ty).visit_vars(&mut |v| {
+            if v >= first {
+                generic.push(v);
+            }
+        });
+        self.generic.extend(generic);
+    }
+
+    // A use of a generic function: its variables replaced by fresh ones
+    fn instantiate(&mut self, ty: &Type) -> Type {
+        let ty = self.resolve(ty);
+        let mut generic = Vec::new();
+        ty.visit_vars(&mut |v| {
+            if self.generic.contains(&v) && !generic.contains(&v) {
+                generic.push(v);
+            }
+        });
+        let mut fresh = HashMap::new();
+        for v in generic {
+            let var = self.fresh();
+            if self.numeric.contains(&v) {
+                self.numeric(&[&var]);
+            }
+            fresh.insert(v, var);
+        }
+        self.resolve(&ty.map_vars(&mut |v, _| fresh.get(&v).cloned()))
+    }
+
+    fn declare_names(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Struct { name, .. } => {
+                self.structs.insert(name.clone(), Vec::new());
+            }
+            StmtKind::Enum { name, .. } => {
+                self.enums.insert(name.clone(), Vec::new());
+            }
+            _ => Self::child_stmts(stmt).into_iter().for_each(|child| self.declare_names(child)),
+        }
+    }
+
+    fn declare(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Struct { name, fields, .. } => {
+                let fields = fields
+                    .iter()
+                    .map(|(field, declared)| (field.clone(), self.annotation(declared, stmt.span)))
+                    .collect();
+                self.structs.insert(name.clone(), fields);
+            }
+            StmtKind::Enum { name, variants, .. } => {
+                let variants = variants
+                    .iter()
+                    .map(|(variant, payload)| {
+                        (variant.clone(), payload.iter().map(|declared| self.annotation(declared, stmt.span)).collect())
+                    })
+                    .collect();
+                self.enums.insert(name.clone(), variants);
+            }
+            StmtKind::Impl { type_name, methods } => {
+                for method in methods {
+                    if let StmtKind::FunctionDef { name, params, return_type, .. } = &method.kind {
+                        let signature = self.signature(params.get(1..).unwrap_or_default(), return_type);
+                        self.methods.entry(type_name.clone()).or_default().insert(name.clone(), signature);
+                    }
+                }
+            }
+            _ => Self::child_stmts(stmt).into_iter().for_each(|child| self.declare(child)),
+        }
+    }
+
+    // Functions may be called before their definition in the same block (and call
+    // themselves), so a block's functions are declared before its statements are checked
+    fn declare_functions(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            if let StmtKind::FunctionDef { name, params, return_type, .. } = &stmt.kind {
+                let signature = self.signature(params, return_type);
+                self.define(name, signature, false);
+            }
+        }
+    }
+
+    fn child_stmts(stmt: &Stmt) -> Vec<&Stmt> {
+        match &stmt.kind {
+            StmtKind::Block(statements) => statements.iter().collect(),
+            StmtKind::IfStmt { then_branch, else_branch, .. } => {
+                std::iter::once(then_branch.as_ref()).chain(else_branch.as_deref()).collect()
+            }
+            StmtKind::FunctionDef { body, .. }
+            | StmtKind::For { body, .. }
+            | StmtKind::While { body, .. }
+            | StmtKind::Parallel { body, .. } => vec![body.as_ref()],
+            StmtKind::Branch { arms, fallback } => arms.iter().map(|(_, body)| body).chain(fallback.as_deref()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // The type an annotation names; references (`&Matrix`) have the type they refer to
+    fn named_type(&self, name: &str) -> Option<Type> {
+        let name = name.trim_start_matches('&').trim_start_matches("mut ");
+        if let Some(numeric_type) = NumericType::from_name(name) {
+            return Some(Type::Number(numeric_type));
+        }
+        Some(match name {
+            "Any" => Type::Any,
+            "String" => Type::String,
+            "Bool" | "Boolean" => Type::Bool,
+            "Nil" => Type::Nil,
+            "Array" | "Slice" => Type::Array(Box::new(Type::Any)),
+            "Map" => Type::Map(Box::new(Type::Any), Box::new(Type::Any)),
+            "Set" => Type::Set(Box::new(Type::Any)),
+            "Function" => Type::Function(None, Box::new(Type::Any)),
+            _ if self.structs.contains_key(name) => Type::Struct(name.to_string()),
+            _ if self.enums.contains_key(name) => Type::Enum(name.to_string()),
+            _ => return None,
+        })
+    }
+
+    // Like `named_type`, reporting names that are not types
+    fn annotation(&mut self, name: &str, span: Span) -> Type {
+        self.named_type(name).unwrap_or_else(|| {
+            self.error(span, format!("unknown type `{}`", name));
+            Type::Any
+        })
+    }
+
+    // Unannotated parameters and results are left to inference. Unknown names are reported
+    // where the definition itself is checked.
+    fn signature(&mut self, params: &[Param], return_type: &Option<String>) -> Type {
+        let mut declared = |annotation: &Option<String>| match annotation {
+            Some(name) => self.named_type(name).unwrap_or(Type::Any),
+            None => self.fresh(),
+        };
+        let params = params.iter().map(|param| declared(&param.ty)).collect();
+        Type::Function(Some(params), Box::new(declared(return_type)))
+    }
+
+    fn define(&mut self, name: &str, ty: Type, mutable: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), (ty, mutable));
+        }
+    }
+
+    // Defines a name the program binds, remembering it for `bindings`
+    fn define_binding(&mut self, name: &str, ty: Type, mutable: bool, span: Span) {
+        let top_level = self.scopes.len() == 1;
+        self.bindings.push(Binding { name: name.to_string(), ty: ty.clone(), span, top_level });
+        self.define(name, ty, mutable);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).map(|(ty, _)| ty)
+    }
+
+    // The enum an expression such as `Shape` in `Shape.Circle(r)` names, unless a variable
+    // of that name hides it
+    fn enum_name(&self, expr: &Expr) -> Option<String> {
+        match &expr.kind {
+            ExprKind::Ident(name) if self.enums.contains_key(name) && self.lookup(name).is_none() => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let { name, type_name, value, mutable, .. } => {
+                let first = self.vars.len() as u32;
+                let found = self.expr(value);
+                let ty = match type_name {
+                    Some(annotation) => {
+                        let declared = self.annotation(annotation, stmt.span);
+                        if !self.fits(&declared, &found) {
+                            let found = self.resolve(&found).normalized();
+                            self.error(value.span, format!("`{}` is declared `{}`, but its value is `{}`", name, declared, found));
+                        }
+                        declared
+                    }
+                    None => found,
+                };
+                // `let id = fn(x) => x` is as generic as a function would be
+                if let ExprKind::Lambda { .. } = value.kind {
+                    self.generalize(&ty, first);
+                }
+                self.define_binding(name, ty, *mutable, stmt.span);
+            }
+            StmtKind::LetPattern { pattern, value, mutable } => {
+                let ty = self.expr(value);
+                self.bind_pattern(pattern, &ty, *mutable);
+            }
+            StmtKind::ExprStmt(expr) => {
+                self.expr(expr);
+            }
+            StmtKind::Block(statements) => {
+                self.scopes.push(HashMap::new());
+                self.declare_functions(statements);
+                for statement in statements {
+                    self.stmt(statement);
+                }
+                self.scopes.pop();
+            }
+            StmtKind::IfStmt { condition, then_branch, else_branch } => {
+                self.expr(condition);
+                self.stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.stmt(else_branch);
+                }
+            }
+            StmtKind::While { condition, body, .. } => {
+                self.expr(condition);
+                self.stmt(body);
+            }
+            StmtKind::FunctionDef { name, params, return_type, .. } => {
+                // Declared with the other functions of its block
+                let signature = match self.lookup(name).cloned() {
+                    Some(signature) => signature,
+                    None => self.signature(params, return_type),
+                };
+                self.define_binding(name, signature.clone(), false, stmt.span);
+                self.function(name, stmt, &signature, None);
+            }
+            StmtKind::Impl { type_name, methods } => {
+                let receiver = self.named_type(type_name).unwrap_or(Type::Any);
+                for method in methods {
+                    if let StmtKind::FunctionDef { name, params, return_type, .. } = &method.kind {
+                        let qualified = format!("{}.{}", type_name, name);
+                        let signature = self.methods.get(type_name).and_then(|methods| methods.get(name)).cloned();
+                        let signature = match signature {
+                            Some(signature) => signature,
+                            None => self.signature(params.get(1..).unwrap_or_default(), return_type),
+                        };
+                        self.function(&qualified, method, &signature, Some(&receiver));
+                    }
+                }
+            }
+            StmtKind::For { pattern, range, body, .. } => {
+                let element = self.loop_element(range);
+                self.scopes.push(HashMap::new());
+                self.bind_pattern(pattern, &element, false);
+                self.stmt(body);
+                self.scopes.pop();
+            }
+            StmtKind::Parallel { var, range, body } => {
+                self.loop_element(range);
+                self.scopes.push(HashMap::new());
+                self.parallel.push(self.scopes.len() - 1);
+                self.define(var, Type::int(), false);
+                self.stmt(body);
+                self.parallel.pop();
+                self.scopes.pop();
+            }
+            StmtKind::Branch { arms, fallback } => {
+                for (condition, body) in arms {
+                    self.expr(condition);
+                    self.stmt(body);
+                }
+                if let Some(fallback) = fallback {
+                    self.stmt(fallback);
+                }
+            }
+            StmtKind::Return(value) => {
+                let found = self.expr(value);
+                let Some(returning) = self.returns.last_mut() else {
+                    return;
+                };
+                returning.seen = true;
+                let returning = returning.clone();
+                if !self.fits(&returning.result, &found) {
+                    match returning.result {
+                        // Returns of different types: the inferred result is any of them
+                        Type::Var(v) if returning.inferred => {
+                            let root = self.root(v);
+                            self.vars[root as usize] = Some(Type::Any);
+                        }
+                        expected => {
+                            let (expected, found) = (self.resolve(&expected).normalized(), self.resolve(&found).normalized());
+                            let message = format!("`{}` returns `{}`, not `{}`", returning.function, expected, found);
+                            self.error(value.span, message);
+                        }
+                    }
+                }
+            }
+            StmtKind::Struct { .. } | StmtKind::Enum { .. } | StmtKind::Break(_) | StmtKind::Continue(_) => {}
+        }
+    }
+
+    // Checks a function or method body with the parameters of its signature in scope,
+    // then makes the variables of the signature that are still unbound generic.
+    // `receiver` is the type of `self` for methods, whose signature leaves `self` out.
+    fn function(&mut self, name: &str, definition: &Stmt, signature: &Type, receiver: Option<&Type>) {
+        let StmtKind::FunctionDef { params, return_type, body, .. } = &definition.kind else {
+            return;
+        };
+        let span = definition.span;
+        let (mut param_types, result) = match signature {
+            Type::Function(Some(types), result) => (types.clone(), (**result).clone()),
+            _ => (Vec::new(), Type::Any),
+        };
+        if let Some(receiver) = receiver {
+            param_types.insert(0, receiver.clone());
+        }
+        let mut first = u32::MAX;
+        signature.visit_vars(&mut |v| first = first.min(v));
+        for param in params {
+            if let Some(annotation) = &param.ty {
+                self.annotation(annotation, param.span);
+            }
+        }
+        if let Some(annotation) = return_type {
+            self.annotation(annotation, span);
+        }
+
+        self.scopes.push(HashMap::new());
+        for (i, param) in params.iter().enumerate() {
+            let ty = param_types.get(i).cloned().unwrap_or(Type::Any);
+            self.define_binding(&param.name, ty, false, param.span);
+        }
+        let inferred = return_type.is_none();
+        self.returns.push(Returning { function: name.to_string(), result: result.clone(), inferred, seen: false });
+        self.stmt(body);
+        self.returns.pop();
+        self.scopes.pop();
+        // Falling off the end of the body returns nil
+        if !always_returns(body) && !self.fits(&result, &Type::Nil) {
+            match result {
+                Type::Var(v) if inferred => {
+                    let root = self.root(v);
+                    self.vars[root as usize] = Some(Type::Any);
+                }
+                expected => self.error(span, format!("not all paths of `{}` return `{}`", name, expected.normalized())),
+            }
+        }
+        self.generalize(signature, first);
+    }
+
+    // The type of the loop variable of a `for` or `parallel` loop over `range`
+    fn loop_element(&mut self, range: &Expr) -> Type {
+        if let ExprKind::Range { .. } = range.kind {
+            self.expr(range);
+            return Type::int();
+        }
+        match self.expr(range) {
+            ty if ty.is_integer() => Type::int(),
+            Type::NumberVar(_) => Type::int(),
+            Type::Array(element) | Type::Set(element) => *element,
+            // Keys are stored as plain Ints whatever integer type they had
+            Type::Map(key, _) if key.is_integer() => Type::int(),
+            Type::Map(key, _) => *key,
+            Type::Any | Type::Var(_) => Type::Any,
+            other => {
+                self.error(range.span, format!("cannot loop over `{}`", other));
+                Type::Any
+            }
+        }
+    }
+
+    // Defines the names a pattern binds, with the types of the parts of `ty` they match
+    fn bind_pattern(&mut self, pattern: &Pattern, ty: &Type, mutable: bool) {
+        let mut ty = self.resolve(ty);
+        if let (PatternKind::Tuple(patterns), Type::Var(_)) = (&pattern.kind, &ty) {
+            // `let (a, b) = pair` makes `pair` a tuple of two
+            let tuple = Type::Tuple(patterns.iter().map(|_| self.fresh()).collect());
+            self.fits(&ty, &tuple);
+            ty = tuple;
+        }
+        match (&pattern.kind, &ty) {
+            (PatternKind::Binding(name), _) if !self.is_variant_name(name) => {
+                self.define_binding(name, ty.clone(), mutable, pattern.span)
+            }
+            (PatternKind::Tuple(patterns), Type::Tuple(types)) if patterns.len() == types.len() => {
+                for (pattern, ty) in patterns.iter().zip(types) {
+                    self.bind_pattern(pattern, ty, mutable);
+                }
+            }
+            (PatternKind::Array(patterns), Type::Array(element)) => {
+                for pattern in patterns {
+                    self.bind_pattern(pattern, element, mutable);
+                }
+            }
+            (PatternKind::Variant { enum_name, name, fields }, _) => {
+                let payload = self.variant_payload(enum_name.as_deref(), name).unwrap_or_default();
+                for (i, field) in fields.iter().enumerate() {
+                    self.bind_pattern(field, payload.get(i).unwrap_or(&Type::Any), mutable);
+                }
+            }
+            (PatternKind::Struct { name, fields }, _) => {
+                let declared = self.structs.get(name).cloned().unwrap_or_default();
+                for (field, pattern) in fields {
+                    let ty = declared.iter().find(|(d, _)| d == field).map_or(Type::Any, |(_, ty)| ty.clone());
+                    self.bind_pattern(pattern, &ty, mutable);
+                }
+            }
+            // Shapes that do not fit are reported by the exhaustiveness checker or at run time
+            (PatternKind::Tuple(patterns) | PatternKind::Array(patterns), _) => {
+                for pattern in patterns {
+                    self.bind_pattern(pattern, &Type::Any, mutable);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_variant_name(&self, name: &str) -> bool {
+        self.enums.values().any(|variants| variants.iter().any(|(variant, _)| variant == name))
+    }
+
+    fn variant_payload(&self, enum_name: Option<&str>, variant: &str) -> Option<Vec<Type>> {
+        self.enums
+            .iter()
+            .filter(|(name, _)| enum_name.is_none_or(|wanted| wanted == name.as_str()))
+            .find_map(|(_, variants)| variants.iter().find(|(name, _)| name == variant).map(|(_, payload)| payload.clone()))
+    }
+
+    // The type of an expression, as far as it is known so far
+    fn expr(&mut self, expr: &Expr) -> Type {
+        let ty = self.expr_type(expr);
+        self.resolve(&ty)
+    }
+
+    fn expr_type(&mut self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Number(_) => Type::int(),
+            ExprKind::Floating(_) => Type::Number(NumericType::Float),
+            ExprKind::Int32(_) => Type::Number(NumericType::Int32),
+            ExprKind::UInt32(_) => Type::Number(NumericType::UInt32),
+            ExprKind::Float32(_) => Type::Number(NumericType::Float32),
+            ExprKind::Boolean(_) => Type::Bool,
+            ExprKind::String(_) => Type::String,
+            ExprKind::InterpolatedString(segments) => {
+                for segment in segments {
+                    if let StringSegment::Expr(inner) = segment {
+                        self.expr(inner);
+                    }
+                }
+                Type::String
+            }
+            ExprKind::Ident(name) => match self.lookup(name).cloned() {
+                Some(ty) => self.instantiate(&ty),
+                None => builtin_function(name).unwrap_or(Type::Any),
+            },
+            ExprKind::Grouping(inner) => self.expr(inner),
+            ExprKind::Unary { op, expr: inner } => {
+                let ty = self.expr(inner);
+                match (op, &ty) {
+                    (Token::Bang, _) => Type::Bool,
+                    (_, Type::Number(_) | Type::Any | Type::Var(_) | Type::NumberVar(_)) => {
+                        self.numeric(&[&ty]);
+                        self.resolve(&ty)
+                    }
+                    _ => {
+                        self.error(expr.span, format!("cannot negate `{}`", ty));
+                        Type::Any
+                    }
+                }
+            }
+            ExprKind::Binary { left, op: Token::AndAnd | Token::OrOr, right } => {
+                self.expr(left);
+                self.expr(right);
+                Type::Bool
+            }
+            ExprKind::Binary { left, op, right } => {
+                let (l, r) = (self.expr(left), self.expr(right));
+                self.binary(op, &l, &r, expr.span)
+            }
+            ExprKind::Assignment { target, value } => {
+                self.check_mutable(target);
+                let (expected, found) = (self.expr(target), self.expr(value));
+                self.check_assignment(target, &expected, &found, value.span);
+                found
+            }
+            ExprKind::CompoundAssignment { target, op, value } => {
+                self.check_mutable(target);
+                let (expected, operand) = (self.expr(target), self.expr(value));
+                let found = self.binary(op, &expected, &operand, expr.span);
+                self.check_assignment(target, &expected, &found, expr.span);
+                found
+            }
+            ExprKind::FunctionCall { callee, arguments } => {
+                let callee_type = self.expr(callee);
+                let name = match &callee.kind {
+                    ExprKind::Ident(name) => name.clone(),
+                    _ => "function".to_string(),
+                };
+                self.call(&name, &callee_type, arguments, expr.span)
+            }
+            ExprKind::Range { start, end, step } => {
+                for bound in [start, end, step].into_iter().flatten() {
+                    let ty = self.expr(bound);
+                    if !ty.is_integer() && !self.fits(&Type::int(), &ty) {
+                        self.error(bound.span, format!("range bounds must be integers, not `{}`", ty));
+                    }
+                }
+                Type::Any
+            }
+            ExprKind::Array(elements) => Type::Array(Box::new(self.element_type(elements))),
+            ExprKind::Set(elements) => Type::Set(Box::new(self.element_type(elements))),
+            ExprKind::Tuple(elements) => Type::Tuple(elements.iter().map(|element| self.expr(element)).collect()),
+            ExprKind::Map(entries) => {
+                let (mut key, mut value) = (None, None);
+                for (k, v) in entries {
+                    let (k, v) = (self.expr(k), self.expr(v));
+                    key = Some(match key {
+                        Some(key) => self.join(key, &k),
+                        None => k,
+                    });
+                    value = Some(match value {
+                        Some(value) => self.join(value, &v),
+                        None => v,
+                    });
+                }
+                // The entries of an empty map are whatever is stored in it later
+                let key = key.unwrap_or_else(|| self.fresh());
+                let value = value.unwrap_or_else(|| self.fresh());
+                Type::Map(Box::new(key), Box::new(value))
+            }
+            ExprKind::Index { object, index } => self.index(object, index),
+            ExprKind::Field { object, name } => self.field(object, name, expr.span),
+            ExprKind::MethodCall { receiver, method, arguments } => self.method_call(receiver, method, arguments, expr.span),
+            ExprKind::Match { subject, arms } => {
+                let subject_type = self.expr(subject);
+                let mut result: Option<Type> = None;
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind_pattern(&arm.pattern, &subject_type, false);
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard);
+                    }
+                    // An arm's value is that of its expression; blocks are not tracked
+                    let arm_type = match &arm.body.kind {
+                        StmtKind::ExprStmt(value) => self.expr(value),
+                        _ => {
+                            self.stmt(&arm.body);
+                            Type::Any
+                        }
+                    };
+                    self.scopes.pop();
+                    result = Some(match result {
+                        Some(result) => self.join(result, &arm_type),
+                        None => arm_type,
+                    });
+                }
+                result.unwrap_or(Type::Any)
+            }
+            ExprKind::Lambda { params, body } => {
+                self.scopes.push(HashMap::new());
+                let mut param_types = Vec::with_capacity(params.len());
+                for param in params {
+                    let ty = match &param.ty {
+                        Some(annotation) => self.annotation(annotation, param.span),
+                        None => self.fresh(),
+                    };
+                    self.define_binding(&param.name, ty.clone(), false, param.span);
+                    param_types.push(ty);
+                }
+                let result = self.fresh();
+                let returning = Returning { function: "<anonymous>".to_string(), result: result.clone(), inferred: true, seen: false };
+                self.returns.push(returning);
+                self.stmt(body);
+                if self.returns.pop().is_some_and(|returning| !returning.seen) {
+                    self.fits(&result, &Type::Nil);
+                }
+                self.scopes.pop();
+                Type::Function(Some(param_types), Box::new(result))
+            }
+            ExprKind::StructLiteral { name, fields } => {
+                let Some(declared) = self.structs.get(name).cloned() else {
+                    self.error(expr.span, format!("unknown struct `{}`", name));
+                    fields.iter().for_each(|(_, value)| {
+                        self.expr(value);
+                    });
+                    return Type::Any;
+                };
+                for (field, value) in fields {
+                    let found = self.expr(value);
+                    match declared.iter().find(|(d, _)| d == field) {
+                        Some((_, expected)) if !self.fits(expected, &found) => {
+                            let (expected, found) = (self.resolve(expected).normalized(), self.resolve(&found).normalized());
+                            self.error(value.span, format!("field `{}` of `{}` is `{}`, not `{}`", field, name, expected, found));
+                        }
+                        Some(_) => {}
+                        None => self.error(value.span, format!("struct `{}` has no field `{}`", name, field)),
+                    }
+                }
+                if let Some((missing, _)) = declared.iter().find(|(d, _)| !fields.iter().any(|(given, _)| given == d)) {
+                    self.error(expr.span, format!("missing field `{}` in `{}` literal", missing, name));
+                }
+                Type::Struct(name.clone())
+            }
+        }
+    }
+
+    // The element type of an array or set literal: that of its elements if they agree
+    fn element_type(&mut self, elements: &[Expr]) -> Type {
+        let mut element: Option<Type> = None;
+        for expr in elements {
+            let ty = self.expr(expr);
+            element = Some(match element {
+                Some(element) => self.join(element, &ty),
+                None => ty,
+            });
+        }
+        // The elements of an empty collection are whatever is stored in it later
+        element.unwrap_or_else(|| self.fresh())
+    }
+
+    // Result type of a binary operator, mirroring `Interpreter::binary_op` and `numeric`.
+    // An operand of unknown type is bound where the other operand decides it, as for
+    // `name + "!"`, and made a number where only numbers work, as for `x * 2` or `x < y`.
+    // With a plain Int, a number of any type keeps its type, so `x * 2` has the type of `x`.
+    fn binary(&mut self, op: &Token, left: &Type, right: &Type, span: Span) -> Type {
+        use Type::{Any, Bool, Number, NumberVar, Var};
+        let mismatch = |this: &mut Self| {
+            let (left, right) = (left.normalized(), right.normalized());
+            this.error(span, format!("cannot apply `{}` to `{}` and `{}`", Self::symbol(op), left, right));
+            Any
+        };
+        let number = |ty: &Type| matches!(ty, Number(_) | Any | Var(_) | NumberVar(_));
+        let integer = |ty: &Type| ty.is_integer() || matches!(ty, Any | Var(_) | NumberVar(_));
+        match op {
+            Token::EQ | Token::NE => Bool,
+            Token::GT | Token::LT | Token::GE | Token::LE => {
+                if number(left) && number(right) {
+                    self.numeric(&[left, right]);
+                } else {
+                    self.error(span, format!("cannot compare `{}` with `{}`", left.normalized(), right.normalized()));
+                }
+                Bool
+            }
+            Token::Plus | Token::Minus | Token::Star | Token::Slash | Token::Percent | Token::StarStar => {
+                match (left, right) {
+                    (Type::String, Type::String) if *op == Token::Plus => Type::String,
+                    (Number(l), Number(r)) => match NumericType::common(*l, *r) {
+                        // A negative integer exponent gives a Float
+                        Ok(common) if *op == Token::StarStar && !common.is_float() => Any,
+                        Ok(common) => Number(common),
+                        Err(_) => self.mismatched_integers(*l, *r, span),
+                    },
+                    (Type::String, Any) | (Any, Type::String) if *op == Token::Plus => Any,
+                    (Var(v), Type::String) | (Type::String, Var(v)) if *op == Token::Plus => {
+                        self.bind(*v, Type::String);
+                        Type::String
+                    }
+                    // Either could still be a String
+                    (Var(_) | Any, Var(_) | Any) if *op == Token::Plus => Any,
+                    (l, r) if number(l) && number(r) => {
+                        self.numeric(&[l, r]);
+                        if *op == Token::StarStar {
+                            Any
+                        } else {
+                            self.numeric_result(l, r)
+                        }
+                    }
+                    _ => mismatch(self),
+                }
+            }
+            Token::Amp | Token::Pipe | Token::Caret => match (left, right) {
+                (Bool, Bool) => Bool,
+                (Number(l), Number(r)) if !l.is_float() && !r.is_float() => match NumericType::common(*l, *r) {
+                    Ok(common) => Number(common),
+                    Err(_) => self.mismatched_integers(*l, *r, span),
+                },
+                (Var(v), Bool) | (Bool, Var(v)) => {
+                    self.bind(*v, Bool);
+                    Bool
+                }
+                // Either could still be a Bool
+                (Var(_) | Any, Var(_) | Any) | (Any, Bool) | (Bool, Any) => Any,
+                (l, r) if integer(l) && integer(r) => {
+                    self.numeric(&[l, r]);
+                    self.numeric_result(l, r)
+                }
+                _ => mismatch(self),
+            },
+            Token::Shl | Token::Shr => match (left, right) {
+                (Any, r) if integer(r) => Any,
+                (l, r) if integer(l) && integer(r) => {
+                    self.numeric(&[l, r]);
+                    self.resolve(l)
+                }
+                _ => mismatch(self),
+            },
+            _ => Any,
+        }
+    }
+
+    // The result of arithmetic on two numbers, at least one of unknown type: the type of
+    // `x` for `x * 2` or `x * x`, and some number otherwise
+    fn numeric_result(&mut self, left: &Type, right: &Type) -> Type {
+        match (self.resolve(left), self.resolve(right)) {
+            (Type::NumberVar(v), other) | (other, Type::NumberVar(v)) if other == Type::int() || other == Type::NumberVar(v) => {
+                Type::NumberVar(v)
+            }
+            (Type::Any, _) | (_, Type::Any) => Type::Any,
+            _ => {
+                let number = self.fresh();
+                self.numeric(&[&number]);
+                self.resolve(&number)
+            }
+        }
+    }
+
+    fn mismatched_integers(&mut self, left: NumericType, right: NumericType, span: Span) -> Type {
+        let message = format!(
+            "mismatched integer types `{}` and `{}`; convert one with i32(x), u32(x) or i64(x)",
+            left.name(),
+            right.name()
+        );
+        self.error(span, message);
+        Type::Any
+    }
+
+    fn symbol(op: &Token) -> String {
+        op.to_string().trim_matches('`').to_string()
+    }
+
+    // Only `let mut` variables can be assigned to. The fields and elements of any variable
+    // can, as the value they belong to is shared rather than copied.
+    fn check_mutable(&mut self, target: &Expr) {
+        let ExprKind::Ident(name) = &target.kind else {
+            return;
+        };
+        let Some(depth) = self.scopes.iter().rposition(|scope| scope.contains_key(name)) else {
+            return;
+        };
+        if self.scopes[depth][name].1 {
+            return;
+        }
+        let message = match self.parallel.last() {
+            Some(&body) if depth < body => format!(
+                "cannot assign to `{}` in a `parallel` body: it is declared outside the loop without `let mut`",
+                name
+            ),
+            _ => format!("cannot assign to immutable `{}`; only `let mut` variables can be assigned", name),
+        };
+        self.error(target.span, message);
+    }
+
+    // `x = value` must fit the type `x` was declared with
+    fn check_assignment(&mut self, target: &Expr, expected: &Type, found: &Type, span: Span) {
+        if !self.fits(expected, found) {
+            let (expected, found) = (self.resolve(expected).normalized(), self.resolve(found).normalized());
+            let target = match &target.kind {
+                ExprKind::Ident(name) => format!("`{}`", name),
+                ExprKind::Field { name, .. } => format!("field `{}`", name),
+                _ => "element".to_string(),
+            };
+            self.error(span, format!("cannot assign `{}` to {} of type `{}`", found, target, expected));
+        }
+    }
+
+    // Checks the arguments of a call to a value of type `callee`
+    fn call(&mut self, name: &str, callee: &Type, arguments: &[Expr], span: Span) -> Type {
+        let found: Vec<Type> = arguments.iter().map(|argument| self.expr(argument)).collect();
+        match callee {
+            Type::Function(Some(params), result) => {
+                if params.len() != found.len() {
+                    self.error(span, format!("`{}` takes {} argument(s), {} given", name, params.len(), found.len()));
+                } else {
+                    for (i, (expected, found)) in params.iter().zip(&found).enumerate() {
+                        if !self.fits(expected, found) {
+                            let (expected, found) = (self.resolve(expected).normalized(), self.resolve(found).normalized());
+                            let message = format!("argument {} of `{}` is `{}`, not `{}`", i + 1, name, expected, found);
+                            self.error(arguments[i].span, message);
+                        }
+                    }
+                }
+                self.resolve(result)
+            }
+            Type::Function(None, result) => (**result).clone(),
+            // `f(x)` on a parameter `f` makes it a function taking `x`
+            Type::Var(_) => {
+                let result = self.fresh();
+                self.fits(callee, &Type::Function(Some(found), Box::new(result.clone())));
+                self.resolve(&result)
+            }
+            Type::Any => Type::Any,
+            other => {
+                self.error(span, format!("`{}` is not a function", other));
+                Type::Any
+            }
+        }
+    }
+
+    fn index(&mut self, object: &Expr, index: &Expr) -> Type {
+        let container = self.expr(object);
+        let index_type = self.expr(index);
+        let is_range = matches!(index.kind, ExprKind::Range { .. });
+        match container {
+            Type::Array(_) if is_range => container,
+            Type::Array(element) => {
+                if !index_type.is_integer() && !self.fits(&Type::int(), &index_type) {
+                    self.error(index.span, format!("index must be an integer, not `{}`", index_type));
+                }
+                *element
+            }
+            Type::Map(key, value) => {
+                if !self.fits(&key, &index_type) {
+                    self.error(index.span, format!("map keys are `{}`, not `{}`", self.resolve(&key), index_type));
+                }
+                self.resolve(&value)
+            }
+            Type::Tuple(elements) => match index.kind {
+                ExprKind::Number(i) if i >= 0 && (i as usize) < elements.len() => elements[i as usize].clone(),
+                ExprKind::Number(i) => {
+                    self.error(index.span, format!("index {} out of bounds for a tuple of {}", i, elements.len()));
+                    Type::Any
+                }
+                _ => Type::Any,
+            },
+            Type::Any | Type::Var(_) => Type::Any,
+            other => {
+                self.error(object.span, format!("cannot index into `{}`", other));
+                Type::Any
+            }
+        }
+    }
+
+    fn field(&mut self, object: &Expr, name: &str, span: Span) -> Type {
+        if let Some(enum_name) = self.enum_name(object) {
+            // `Color.Red`: a variant without payload
+            return self.variant(&enum_name, name, &[], span);
+        }
+        match self.expr(object) {
+            Type::Struct(struct_name) => {
+                let field = self.structs.get(&struct_name).and_then(|fields| fields.iter().find(|(f, _)| f == name));
+                match field {
+                    Some((_, ty)) => ty.clone(),
+                    None => {
+                        self.error(span, format!("struct `{}` has no field `{}`", struct_name, name));
+                        Type::Any
+                    }
+                }
+            }
+            Type::Array(_) | Type::String | Type::Map(..) | Type::Set(_) | Type::Tuple(_) if name == "length" => Type::int(),
+            Type::Any | Type::Var(_) => Type::Any,
+            other => {
+                self.error(span, format!("`{}` has no field `{}`", other, name));
+                Type::Any
+            }
+        }
+    }
+
+    // `Shape.Circle(r)`: the payload must fit the variant's declared types
+    fn variant(&mut self, enum_name: &str, variant: &str, arguments: &[Expr], span: Span) -> Type {
+        let found: Vec<Type> = arguments.iter().map(|argument| self.expr(argument)).collect();
+        let Some(payload) = self.variant_payload(Some(enum_name), variant) else {
+            self.error(span, format!("enum `{}` has no variant `{}`", enum_name, variant));
+            return Type::Any;
+        };
+        if payload.len() != found.len() {
+            let message = format!("`{}.{}` holds {} value(s), {} given", enum_name, variant, payload.len(), found.len());
+            self.error(span, message);
+        } else {
+            for (i, (expected, found)) in payload.iter().zip(&found).enumerate() {
+                if !self.fits(expected, found) {
+                    let (expected, found) = (self.resolve(expected).normalized(), self.resolve(found).normalized());
+                    let message = format!("value {} of `{}.{}` is `{}`, not `{}`", i + 1, enum_name, variant, expected, found);
+                    self.error(arguments[i].span, message);
+                }
+            }
+        }
+        Type::Enum(enum_name.to_string())
+    }
+
+    // Methods from `impl` blocks first, then a struct field holding a function, then the
+    // built-in methods, as the interpreter dispatches them
+    fn method_call(&mut self, receiver: &Expr, method: &str, arguments: &[Expr], span: Span) -> Type {
+        if let Some(enum_name) = self.enum_name(receiver) {
+            return self.variant(&enum_name, method, arguments, span);
+        }
+        let receiver_type = self.expr(receiver);
+        let Some(impl_name) = receiver_type.impl_name() else {
+            arguments.iter().for_each(|argument| {
+                self.expr(argument);
+            });
+            return Type::Any;
+        };
+        let qualified = format!("{}.{}", impl_name, method);
+        if let Some(signature) = self.methods.get(&impl_name).and_then(|methods| methods.get(method)).cloned() {
+            let signature = self.instantiate(&signature);
+            return self.call(&qualified, &signature, arguments, span);
+        }
+        if let Type::Struct(struct_name) = &receiver_type {
+            let field = self.structs.get(struct_name).and_then(|fields| fields.iter().find(|(f, _)| f == method)).cloned();
+            if let Some((_, field_type)) = field {
+                return self.call(&qualified, &field_type, arguments, span);
+            }
+        }
+        let found: Vec<Type> = arguments.iter().map(|argument| self.expr(argument)).collect();
+        // What is pushed decides the elements of an empty array
+        if let (Type::Array(element), "push", [item]) = (&receiver_type, method, found.as_slice()) {
+            if !self.fits(element, item) {
+                let message = format!("cannot push `{}` onto `{}`", item.normalized(), self.resolve(&receiver_type).normalized());
+                self.error(arguments[0].span, message);
+            }
+        }
+        builtin_method(&receiver_type, method, arguments.len()).unwrap_or_else(|| {
+            let message = format!("`{}` has no method `{}` taking {} argument(s)", receiver_type, method, arguments.len());
+            self.error(span, message);
+            Type::Any
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> (TypeChecker, Vec<String>) {
+        let tokens = Lexer::new(source).tokenize().expect("source should lex");
+        let program = Parser::new(tokens).parse().expect("source should parse");
+        let mut checker = TypeChecker::new();
+        let errors = checker.check(&program).into_iter().map(|e| e.message).collect();
+        (checker, errors)
+    }
+
+    // `name: Type` for every binding of a program that checks
+    fn types(source: &str) -> Vec<String> {
+        let (checker, errors) = check(source);
+        assert_eq!(errors, Vec::<String>::new());
+        checker.bindings().iter().map(|binding| format!("{}: {}", binding.name, binding.ty)).collect()
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        check(source).1
+    }
+
+    #[test]
+    fn declared_results_must_be_returned_on_every_path() {
+        let source = "fn sign(x: Int) -> Int:\n    if x > 0:\n        return 1\n";
+        assert_eq!(errors(source), vec!["not all paths of `sign` return `Int`"]);
+        assert_eq!(errors("fn sign(x: Int) -> Int:\n    if x > 0:\n        return 1\n    else:\n        return -1\n"), Vec::<String>::new());
+        assert_eq!(types("fn maybe(x: Int):\n    if x > 0:\n        return 1\n")[0], "maybe: fn(Int) -> Any");
+    }
+
+    #[test]
+    fn mismatches_are_reported() {
+        assert_eq!(errors("let n: Int = \"ten\"\n"), vec!["`n` is declared `Int`, but its value is `String`"]);
+        assert_eq!(errors("let s = \"a\" - 1\n"), vec!["cannot apply `-` to `String` and `Int`"]);
+        assert_eq!(errors("fn f(x: Int):\n    return x\nf(true)\n"), vec!["argument 1 of `f` is `Int`, not `Bool`"]);
+    }
+
+    #[test]
+    fn mismatches_name_types_the_way_they_are_shown() {
+        // Earlier code uses up type variables, which must not show through
+        let earlier = "let first = []\nlet second = []\n";
+        let source = format!("{}fn f(n) -> Int:\n    return [n * 2]\n", earlier);
+        assert_eq!(errors(&source), vec!["`f` returns `Int`, not `Array<Number>`"]);
+        let source = format!("{}struct P: x: Int\nlet p = P {{ x: [] }}\n", earlier);
+        assert_eq!(errors(&source), vec!["field `x` of `P` is `Int`, not `Array<a>`"]);
+        let source = format!("{}enum E: V(Int)\nlet e = E.V([])\n", earlier);
+        assert_eq!(errors(&source), vec!["value 1 of `E.V` is `Int`, not `Array<a>`"]);
+    }
+
+    #[test]
+    fn lets_take_the_type_of_their_value() {
+        assert_eq!(types("let a = 1\nlet b = 2.5 * a\nlet c = [\"x\"]\nlet d = (a, c)\n"), vec![
+            "a: Int",
+            "b: Float",
+            "c: Array<String>",
+            "d: (Int, Array<String>)",
+        ]);
+    }
+
+    #[test]
+    fn parameters_and_results_are_inferred() {
+        let source = "fn fact(n):\n    if n <= 1:\n        return 1\n    return n * fact(n - 1)\nfn greet(name):\n    return name + \"!\"\n";
+        assert_eq!(types(source), vec!["fact: fn(Int) -> Int", "n: Int", "greet: fn(String) -> String", "name: String"]);
+    }
+
+    #[test]
+    fn unconstrained_parameters_are_generic() {
+        let source = "fn twice(f, x):\n    return f(f(x))\nlet a = twice(fn(n) => n + 1, 1)\nlet b = twice(fn(s) => s + \"!\", \"a\")\n";
+        let types = types(source);
+        assert_eq!(types[0], "twice: fn(fn(a) -> a, a) -> a");
+        assert_eq!(types[1..3], ["f: fn(a) -> a", "x: a"]);
+        assert!(types.contains(&"a: Int".to_string()) && types.contains(&"b: String".to_string()));
+    }
+
+    #[test]
+    fn arithmetic_makes_parameters_numbers() {
+        let source = "fn scale(x):\n    return x * 2\nlet a = scale(3)\nlet b = scale(1.5)\n";
+        assert_eq!(types(source), vec!["scale: fn(Number) -> Number", "x: Number", "a: Int", "b: Float"]);
+        assert_eq!(errors("fn scale(x):\n    return x * 2\nscale(\"s\")\n"), vec!["argument 1 of `scale` is `Number`, not `String`"]);
+    }
+
+    #[test]
+    fn a_function_and_its_parameters_share_names() {
+        assert_eq!(types("fn pair(a, b):\n    return (a, b)\n"), vec!["pair: fn(a, b) -> (a, b)", "a: a", "b: b"]);
+    }
+
+    #[test]
+    fn empty_arrays_are_filled_in_by_push() {
+        assert_eq!(types("let items = []\nitems.push(1)\n"), vec!["items: Array<Int>"]);
+        assert_eq!(errors("let items = []\nitems.push(1)\nitems.push(\"two\")\n"), vec!["cannot push `String` onto `Array<Int>`"]);
+    }
+
+    #[test]
+    fn only_let_mut_variables_are_reassigned() {
+        assert_eq!(errors("let x = 1\nx = 2\n"), vec!["cannot assign to immutable `x`; only `let mut` variables can be assigned"]);
+        assert_eq!(errors("let mut x = 1\nx += 2\nlet a = [1]\na[0] = 5\n"), Vec::<String>::new());
+        assert_eq!(
+            errors("let total = 0\nparallel i in 0..4:\n    total = i\n"),
+            vec!["cannot assign to `total` in a `parallel` body: it is declared outside the loop without `let mut`"]
+        );
+    }
+}
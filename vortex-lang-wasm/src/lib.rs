@@ -1,42 +1,106 @@
+// The language itself is compiled from the sources of the command-line interpreter, so
+// the browser REPL lexes, checks and runs code exactly as `vortex-lang` does. Parts of
+// them are only used by its terminal REPL.
+#![allow(dead_code)]
+
 use wasm_bindgen::prelude::*;
 use std::panic;
 use std::cell::RefCell;
 
-thread_local! {
-    static INTERPRETER: RefCell<Interpreter> = RefCell::new(Interpreter::new());
+#[path = "../../src/ast.rs"]
+mod ast;
+#[path = "../../src/builtins.rs"]
+mod builtins;
+#[path = "../../src/checker.rs"]
+mod checker;
+#[path = "../../src/gpu_runtime.rs"]
+mod gpu_runtime;
+#[path = "../../src/interpreter.rs"]
+mod interpreter;
+#[path = "../../src/lexer.rs"]
+mod lexer;
+#[path = "../../src/numeric.rs"]
+mod numeric;
+#[path = "../../src/parser.rs"]
+mod parser;
+#[path = "../../src/token.rs"]
+mod token;
+#[path = "../../src/typeck.rs"]
+mod typeck;
+
+use checker::Checker;
+use interpreter::Interpreter;
+use lexer::Lexer;
+use parser::Parser;
+use typeck::TypeChecker;
+
+// Errors point at `<input>:line:col`, the input being what was just entered
+const SOURCE_NAME: &str = "<input>";
+
+// Declarations persist from one input to the next, as in the terminal REPL
+struct Session {
+    interpreter: Interpreter,
+    checker: Checker,
+    type_checker: TypeChecker,
 }
 
-pub mod vortex_core;
-use vortex_core::lexer::Lexer;
-use vortex_core::parser::Parser;
-use vortex_core::interpreter::Interpreter;
-use vortex_core::token::Token;
+thread_local! {
+    static SESSION: RefCell<Session> = RefCell::new(Session::new());
+}
 
 #[wasm_bindgen]
 pub fn run_vortex(input: &str) -> String {
     // Set panic hook for better error messages in WASM
     panic::set_hook(Box::new(console_error_panic_hook::hook));
-    
-    // Lexing
-    let mut lexer = Lexer::new(input);
-    let mut tokens = Vec::new();
-    loop {
-        let token = lexer.next_token();
-        if token == Token::EOF { break; }
-        tokens.push(token);
+
+    SESSION.with(|cell| cell.borrow_mut().run(input))
+}
+
+impl Session {
+    fn new() -> Self {
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        Session { interpreter, checker: Checker::new(), type_checker: TypeChecker::new() }
     }
 
-    // Parsing
-    let mut parser = Parser::new(tokens);
-    let program = parser.parse();
-
-    // Interpret using persistent interpreter
-    INTERPRETER.with(|cell| {
-        let mut interpreter = cell.borrow_mut();
-        match interpreter.interpret(program) {
-            Ok(Some(val)) => format!("{}\n", val),
-            Ok(None) => "✅ Execution completed successfully.\n".to_string(),
-            Err(e) => format!("❌ Runtime error: {}\n", e),
+    // What `print` wrote, followed by any errors; nothing runs unless the input lexes,
+    // parses and checks cleanly
+    fn run(&mut self, input: &str) -> String {
+        let tokens = match Lexer::new(input).tokenize() {
+            Ok(tokens) => tokens,
+            Err(errors) => return report(errors.iter().map(|e| (e.span, "lexical error", e.kind.to_string()))),
+        };
+        let statements = match Parser::new(tokens).parse() {
+            Ok(statements) => statements,
+            Err(errors) => return report(errors.iter().map(|e| (e.span, "syntax error", e.kind.to_string()))),
+        };
+
+        let errors = self.checker.check(&statements);
+        if !errors.is_empty() {
+            return report(errors.into_iter().map(|e| (e.span, "error", e.message)));
+        }
+        let errors = self.type_checker.check(&statements);
+        if !errors.is_empty() {
+            return report(errors.into_iter().map(|e| (e.span, "type error", e.message)));
+        }
+
+        let result = self.interpreter.interpret(statements);
+        let mut output = self.interpreter.take_output();
+        match result {
+            Ok(()) if output.is_empty() => "✅ Execution completed successfully.\n".to_string(),
+            Ok(()) => output,
+            Err(e) => {
+                match e.span {
+                    Some(span) => output.push_str(&format!("❌ {}:{}: runtime error: {}\n", SOURCE_NAME, span, e.message)),
+                    None => output.push_str(&format!("❌ Runtime error: {}\n", e.message)),
+                }
+                output
+            }
         }
-    })
+    }
+}
+
+// One `<input>:line:col: kind: message` line per error
+fn report(errors: impl Iterator<Item = (token::Span, &'static str, String)>) -> String {
+    errors.map(|(span, kind, message)| format!("❌ {}:{}: {}: {}\n", SOURCE_NAME, span, kind, message)).collect()
 }
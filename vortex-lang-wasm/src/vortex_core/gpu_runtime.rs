@@ -1,185 +0,0 @@
-// || shree ganesh ||
-// GPURuntime handles registration and execution of GPU-accelerated code
-
-use super::ast::{Expr, Stmt};
-use super::interpreter::Value;
-use std::collections::HashMap;
-use std::thread;
-use std::time::Duration;
-
-#[derive(Debug)]
-pub struct GPURuntime {
-    functions: HashMap<String, (Vec<(String, String)>, Box<Stmt>)>, // name => (params, body)
-    available: bool,
-    parallel_threads: usize,
-}
-
-impl GPURuntime {
-    pub fn new() -> Self {
-        // In future: actually test CUDA availability
-        println!("[GPU] Initializing GPU runtime");
-        println!("[GPU] Looking for compatible hardware...");
-        println!("[GPU] Using simulation mode");
-        
-        Self {
-            functions: HashMap::new(),
-            available: true,
-            parallel_threads: 4, // Simulate 4 GPU threads by default
-        }
-    }
-
-    pub fn is_available(&self) -> bool {
-        if self.available {
-            println!("[GPU] GPU acceleration is available");
-        } else {
-            println!("[GPU] GPU acceleration is not available, using CPU fallback");
-        }
-        self.available
-    }
-
-    pub fn register_function(
-        &mut self,
-        name: String,
-        params: Vec<(String, String)>,
-        body: &Box<Stmt>,
-    ) -> Result<(), String> {
-        println!("[GPU] Registering GPU function: {}", name);
-        println!("[GPU] Function parameters: {:?}", params);
-        
-        // In a real implementation, we would compile the function to GPU code here
-        println!("[GPU] Simulating JIT compilation for function");
-        
-        self.functions.insert(name.clone(), (params, body.clone()));
-        println!("[GPU] Successfully registered function: {}", name);
-        
-        Ok(())
-    }
-
-    pub fn execute_parallel(
-        &mut self,
-        var: &String,
-        range_expr: &Expr,
-        _body: &Box<Stmt>,
-    ) -> Result<(), String> {
-        // In real CUDA, you'd translate and compile kernels.
-        // Here we just simulate loop body execution in parallel
-        println!("[GPU] Executing parallel loop with variable '{}'", var);
-        
-        match range_expr {
-            Expr::Number(n) => {
-                let n = *n;
-                println!("[GPU] Simulating parallel loop over range 0..{}", n);
-                println!("[GPU] Allocating {} threads on simulated GPU", self.parallel_threads);
-                
-                // Simulate parallel execution using multiple threads
-                let handles: Vec<_> = (0..self.parallel_threads.min(n as usize))
-                    .map(|thread_id| {
-                        // Calculate the range of iterations this thread handles
-                        let chunk_size = (n as usize + self.parallel_threads - 1) / self.parallel_threads;
-                        let start = thread_id * chunk_size;
-                        let end = std::cmp::min(start + chunk_size, n as usize);
-                        
-                        println!("[GPU] Thread {} processing iterations {}..{}", thread_id, start, end);
-                        
-                        // Spawn a real thread to simulate GPU thread
-                        thread::spawn(move || {
-                            for i in start..end {
-                                // Simulate some computation time
-                                thread::sleep(Duration::from_millis(10));
-                                println!("[GPU::Thread{}] Processing iteration {}", thread_id, i);
-                            }
-                        })
-                    })
-                    .collect();
-                
-                // Wait for all simulated GPU threads to complete
-                for (i, handle) in handles.into_iter().enumerate() {
-                    handle.join().unwrap();
-                    println!("[GPU] Thread {} completed", i);
-                }
-                
-                println!("[GPU] Parallel execution complete");
-                Ok(())
-            },
-            Expr::Range { start, end } => {
-                match (&**start, &**end) {
-                    (Expr::Number(start_val), Expr::Number(end_val)) => {
-                        println!("[GPU] Simulating parallel loop over range {}..{}", start_val, end_val);
-                        
-                        let range_size = end_val - start_val;
-                        if range_size <= 0 {
-                            println!("[GPU] Empty or invalid range, nothing to execute");
-                            return Ok(());
-                        }
-                        
-                        println!("[GPU] Executing loop body {} times in parallel", range_size);
-                        for i in *start_val..*end_val {
-                            println!("[GPU::Sim] Iteration {i}");
-                        }
-                        Ok(())
-                    },
-                    _ => Err("GPU parallel loop range bounds must be numeric literals".to_string()),
-                }
-            },
-            _ => Err("GPU parallel loop expects numeric range".to_string()),
-        }
-    }
-
-    pub fn execute_function(&self, name: &str, args: Vec<Value>) -> Result<Value, String> {
-        // Check if function is GPU-registered
-        if !self.functions.contains_key(name) {
-            return Err(format!("GPU function '{}' not found", name));
-        }
-
-        println!("[GPU] Executing function: {}", name);
-        println!("[GPU] Arguments: {:?}", args);
-        
-        // Get the function definition
-        let (params, _body) = self.functions.get(name).unwrap();
-        
-        // Check if argument count matches parameter count
-        if args.len() != params.len() {
-            return Err(format!(
-                "GPU function '{}' expects {} arguments, but got {}", 
-                name, params.len(), args.len()
-            ));
-        }
-        
-        println!("[GPU] Simulating kernel launch on GPU");
-        println!("[GPU] Transferring data to GPU memory");
-        
-        // Simulate execution time
-        thread::sleep(Duration::from_millis(100));
-        
-        println!("[GPU] Executing kernel");
-        thread::sleep(Duration::from_millis(50));
-        
-        println!("[GPU] Kernel execution complete");
-        println!("[GPU] Transferring results back from GPU memory");
-        
-        // In a real implementation, we would execute the function on the GPU
-        // and return the actual result
-        
-        // For now, just return a dummy result
-        match name {
-            "add_gpu" => {
-                if args.len() >= 2 {
-                    match (&args[0], &args[1]) {
-                        (Value::Number(a), Value::Number(b)) => {
-                            let result = a + b;
-                            println!("[GPU] Function returned: {}", result);
-                            return Ok(Value::Number(result));
-                        },
-                        _ => {}
-                    }
-                }
-                println!("[GPU] Function returned default value");
-                Ok(Value::Number(42)) // Default result
-            },
-            _ => {
-                println!("[GPU] Function returned nil");
-                Ok(Value::Nil)
-            }
-        }
-    }
-}
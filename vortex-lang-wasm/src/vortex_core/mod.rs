@@ -1,6 +0,0 @@
-pub mod ast;
-pub mod interpreter;
-pub mod lexer;
-pub mod parser;
-pub mod token;
-pub mod gpu_runtime;